@@ -3,11 +3,12 @@ use std::env;
 use cargo::ops::CompileOptions;
 use cargo::ops;
 use cargo::util::important_paths::{find_root_manifest_for_cwd};
-use cargo::util::{CliResult, CliError, Config};
+use cargo::util::{CliResult, CliError, human, Config};
 
 #[derive(RustcDecodable)]
 struct Options {
-    flag_package: Option<String>,
+    flag_package: Vec<String>,
+    flag_all: bool,
     flag_jobs: Option<u32>,
     flag_features: Vec<String>,
     flag_no_default_features: bool,
@@ -15,6 +16,7 @@ struct Options {
     flag_manifest_path: Option<String>,
     flag_verbose: bool,
     flag_release: bool,
+    flag_profile: Option<String>,
     flag_lib: bool
 }
 
@@ -25,25 +27,29 @@ Usage:
     cargo build [options]
 
 Options:
-    -h, --help               Print this message
-    -p SPEC, --package SPEC  Package to build
-    -j N, --jobs N           The number of jobs to run in parallel
-    --lib                    Build only lib (if present in package)
-    --release                Build artifacts in release mode, with optimizations
-    --features FEATURES      Space-separated list of features to also build
-    --no-default-features    Do not build the `default` feature
-    --target TRIPLE          Build for the target triple
-    --manifest-path PATH     Path to the manifest to compile
-    -v, --verbose            Use verbose output
+    -h, --help                   Print this message
+    -p SPEC, --package SPEC ...  Package to build
+    --all                        Build all packages in the workspace
+    -j N, --jobs N               The number of jobs to run in parallel
+    --lib                        Build only lib (if present in package)
+    --release                    Build artifacts in release mode, with optimizations
+    --profile NAME               Build artifacts with the custom profile NAME
+    --features FEATURES          Space-separated list of features to also build
+    --no-default-features        Do not build the `default` feature
+    --target TRIPLE              Build for the target triple
+    --manifest-path PATH         Path to the manifest to compile
+    -v, --verbose                Use verbose output
 
 If the --package argument is given, then SPEC is a package id specification
 which indicates which package should be built. If it is not given, then the
-current package is built. For more information on SPEC and its format, see the
-`cargo help pkgid` command.
+current package is built. The argument may be given several times to build
+several packages, and --all builds every member of the workspace. For more
+information on SPEC and its format, see the `cargo help pkgid` command.
 
 Compilation can be configured via the use of profiles which are configured in
 the manifest. The default profile for this command is `dev`, but passing
-the --release flag will use the `release` profile instead.
+the --release flag will use the `release` profile instead. The --profile flag
+selects a custom profile of the manifest, which inherits from either of them.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -52,6 +58,11 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
 
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
 
+    if options.flag_release && options.flag_profile.is_some() {
+        return Err(CliError::from_boxed(
+            human("specify either `--release` or `--profile`, not both"), 1))
+    }
+
     let env = if options.flag_release {
         "release"
     } else {
@@ -66,8 +77,12 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         dev_deps: false,
         features: &options.flag_features,
         no_default_features: options.flag_no_default_features,
-        spec: options.flag_package.as_ref().map(|s| &s[..]),
+        spec: &options.flag_package,
+        all_members: options.flag_all,
         lib_only: options.flag_lib,
+        bin_only: None,
+        target_rustc_args: None,
+        profile: options.flag_profile.as_ref().map(|s| &s[..]),
         exec_engine: None,
     };
 
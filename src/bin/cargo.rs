@@ -22,6 +22,8 @@ use cargo::util::{CliError, CliResult, lev_distance, Config};
 struct Flags {
     flag_list: bool,
     flag_verbose: bool,
+    flag_frozen: bool,
+    flag_offline: bool,
     arg_command: String,
     arg_args: Vec<String>,
 }
@@ -38,6 +40,9 @@ Options:
     -V, --version    Print version info and exit
     --list           List installed commands
     -v, --verbose    Use verbose output
+    --frozen         Require Cargo.lock to be up to date and never access the
+                     network
+    --offline        Never access the network, only use what was fetched before
 
 Some common cargo commands are:
     build       Compile the current project
@@ -69,6 +74,7 @@ macro_rules! each_subcommand{ ($mac:ident) => ({
     $mac!(help);
     $mac!(locate_project);
     $mac!(login);
+    $mac!(metadata);
     $mac!(new);
     $mac!(owner);
     $mac!(package);
@@ -76,9 +82,12 @@ macro_rules! each_subcommand{ ($mac:ident) => ({
     $mac!(publish);
     $mac!(read_manifest);
     $mac!(run);
+    $mac!(rustc);
     $mac!(search);
     $mac!(test);
+    $mac!(tree);
     $mac!(update);
+    $mac!(vendor);
     $mac!(verify_project);
     $mac!(version);
     $mac!(yank);
@@ -91,6 +100,8 @@ macro_rules! each_subcommand{ ($mac:ident) => ({
 */
 fn execute(flags: Flags, config: &Config) -> CliResult<Option<()>> {
     config.shell().set_verbose(flags.flag_verbose);
+    config.set_frozen(flags.flag_frozen);
+    config.set_offline(flags.flag_offline);
 
     init_git_transports(config);
 
@@ -131,8 +142,15 @@ fn execute(flags: Flags, config: &Config) -> CliResult<Option<()>> {
 
         // For all other invocations, we're of the form `cargo foo args...`. We
         // use the exact environment arguments to preserve tokens like `--` for
-        // example.
-        _ => env::args().collect(),
+        // example, only taking out the global flags which may also follow the
+        // subcommand.
+        _ => {
+            let mut args = env::args().collect::<Vec<_>>();
+            let (frozen, offline) = take_global_flags(&mut args);
+            if frozen { config.set_frozen(true) }
+            if offline { config.set_offline(true) }
+            args
+        }
     };
 
     macro_rules! cmd{ ($name:ident) => (
@@ -153,6 +171,23 @@ fn execute(flags: Flags, config: &Config) -> CliResult<Option<()>> {
     Ok(None)
 }
 
+/// Removes `--frozen` and `--offline` from `args`, wherever they appear before
+/// a `--`, and returns whether each of them was given.
+fn take_global_flags(args: &mut Vec<String>) -> (bool, bool) {
+    let (mut frozen, mut offline) = (false, false);
+    let mut i = 1;
+    while i < args.len() {
+        match &args[i][..] {
+            "--" => break,
+            "--frozen" => frozen = true,
+            "--offline" => offline = true,
+            _ => { i += 1; continue }
+        }
+        args.remove(i);
+    }
+    (frozen, offline)
+}
+
 fn find_closest(cmd: &str) -> Option<String> {
     match list_commands().iter()
                             // doing it this way (instead of just .min_by(|c|
@@ -6,7 +6,8 @@ use cargo::util::important_paths::{find_root_manifest_for_cwd};
 
 #[derive(RustcDecodable)]
 struct Options {
-    flag_package: Option<String>,
+    flag_package: Vec<String>,
+    flag_all: bool,
     flag_target: Option<String>,
     flag_manifest_path: Option<String>,
     flag_verbose: bool,
@@ -19,16 +20,18 @@ Usage:
     cargo clean [options]
 
 Options:
-    -h, --help               Print this message
-    -p SPEC, --package SPEC  Package to clean artifacts for
-    --manifest-path PATH     Path to the manifest to the package to clean
-    --target TRIPLE          Target triple to clean output for (default all)
-    -v, --verbose            Use verbose output
+    -h, --help                   Print this message
+    -p SPEC, --package SPEC ...  Package to clean artifacts for
+    --all                        Clean the artifacts of all packages
+    --manifest-path PATH         Path to the manifest to the package to clean
+    --target TRIPLE              Target triple to clean output for (default all)
+    -v, --verbose                Use verbose output
 
 If the --package argument is given, then SPEC is a package id specification
-which indicates which package's artifacts should be cleaned out. If it is not
-given, then all packages' artifacts are removed. For more information on SPEC
-and its format, see the `cargo help pkgid` command.
+which indicates which package's artifacts should be cleaned out. It may be
+given several times. If it is not given, or --all is passed, then all packages'
+artifacts are removed. For more information on SPEC and its format, see the
+`cargo help pkgid` command.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -36,9 +39,10 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     debug!("executing; cmd=cargo-clean; args={:?}", env::args().collect::<Vec<_>>());
 
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+    let spec = if options.flag_all {Vec::new()} else {options.flag_package};
     let opts = ops::CleanOptions {
         config: config,
-        spec: options.flag_package.as_ref().map(|s| &s[..]),
+        spec: &spec,
         target: options.flag_target.as_ref().map(|s| &s[..]),
     };
     ops::clean(&root, &opts).map(|_| None).map_err(|err| {
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use cargo::ops;
 use cargo::util::important_paths::find_root_manifest_for_cwd;
 use cargo::util::{CliResult, CliError, Config};
@@ -5,7 +7,15 @@ use cargo::util::{CliResult, CliError, Config};
 #[derive(RustcDecodable)]
 struct Options {
     flag_verbose: bool,
-    flag_manifest_path: Option<String>
+    flag_manifest_path: Option<String>,
+    flag_bump_revision: bool,
+    flag_feature_packages: bool,
+    flag_target: Option<String>,
+    flag_dry_run: bool,
+    flag_diff: bool,
+    flag_recursive: bool,
+    flag_packages: Option<String>,
+    flag_output: Option<String>,
 }
 
 pub const USAGE: &'static str = "
@@ -17,11 +27,31 @@ Usage:
 Options:
     -h, --help               Print this message
     --manifest-path PATH     Path to the manifest to debianize
+    --bump-revision          Add a changelog entry with a new Debian revision
+                             even if the upstream version did not change
+    --feature-packages       Generate a separate -dev package per optional
+                             feature of the crate
+    --target TRIPLE          Build for the target triple instead of the host
+                             rustc runs on
+    --dry-run                Don't write anything, fail if any file in the
+                             debian directory would change
+    --diff                   Don't write anything, print the changes as a
+                             unified diff instead
+    --recursive              Also package all dependencies that are not yet
+                             available in the archive
+    --packages FILE          Packages file listing the crates already
+                             available in the archive
+    --output DIR             Directory to unpack dependencies into when
+                             packaging recursively, target/debian by default
     -v, --verbose            Use verbose output
 
 Uses crago information to setup an initial debian directory used to
-package a rust library or binary for Debian. Doesn't ever override a
-file if it already exists.
+package a rust library or binary for Debian. An existing changelog gets
+a new entry whenever the version in the manifest changed.
+
+With --recursive, every registry or git dependency whose -dev package is
+not listed in the given Packages file gets unpacked into its own directory
+below the output directory and debianized as well.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -30,6 +60,14 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
 
     let opts = ops::DebianizeOptions {
         config: config,
+        target: options.flag_target.as_ref().map(|t| &t[..]),
+        bump_revision: options.flag_bump_revision,
+        feature_packages: options.flag_feature_packages,
+        dry_run: options.flag_dry_run,
+        diff: options.flag_diff,
+        recursive: options.flag_recursive,
+        packages_file: options.flag_packages.as_ref().map(|p| Path::new(p)),
+        output_dir: options.flag_output.as_ref().map(|p| Path::new(p)),
     };
 
     match ops::debianize(&root, &opts) {
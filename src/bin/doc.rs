@@ -1,3 +1,5 @@
+use std::slice;
+
 use cargo::ops;
 use cargo::util::{CliResult, CliError, Config};
 use cargo::util::important_paths::{find_root_manifest_for_cwd};
@@ -56,8 +58,13 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
             dev_deps: false,
             features: &options.flag_features,
             no_default_features: options.flag_no_default_features,
-            spec: options.flag_package.as_ref().map(|s| &s[..]),
+            spec: options.flag_package.as_ref().map(slice::ref_slice)
+                        .unwrap_or(&[]),
+            all_members: false,
             lib_only: false,
+            bin_only: None,
+            target_rustc_args: None,
+            profile: None,
             exec_engine: None,
         },
     };
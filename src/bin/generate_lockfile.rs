@@ -8,6 +8,7 @@ use cargo::util::important_paths::find_root_manifest_for_cwd;
 struct Options {
     flag_manifest_path: Option<String>,
     flag_verbose: bool,
+    flag_minimal_versions: bool,
 }
 
 pub const USAGE: &'static str = "
@@ -19,7 +20,12 @@ Usage:
 Options:
     -h, --help              Print this message
     --manifest-path PATH    Path to the manifest to generate a lockfile for
+    --minimal-versions      Pick the lowest version matching each requirement
     -v, --verbose           Use verbose output
+
+With --minimal-versions every dependency is resolved to the oldest version
+its requirement allows instead of the newest one, which can be used to check
+that the lower bounds of the requirements are actually sufficient.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
@@ -27,6 +33,6 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     config.shell().set_verbose(options.flag_verbose);
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
 
-    ops::generate_lockfile(&root, config)
+    ops::generate_lockfile(&root, config, options.flag_minimal_versions)
         .map(|_| None).map_err(|err| CliError::from_boxed(err, 101))
 }
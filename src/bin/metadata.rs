@@ -0,0 +1,54 @@
+use std::env;
+
+use cargo::ops::{self, ExportInfo};
+use cargo::util::{CliResult, CliError, Config};
+use cargo::util::important_paths::find_root_manifest_for_cwd;
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_features: Vec<String>,
+    flag_no_default_features: bool,
+    flag_no_deps: bool,
+    flag_format_version: Option<u32>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: bool,
+}
+
+pub const USAGE: &'static str = "
+Output the resolved dependencies of a project, the concrete used versions
+including overrides, in machine-readable format.
+
+Usage:
+    cargo metadata [options]
+
+Options:
+    -h, --help                 Print this message
+    --features FEATURES        Space-separated list of features to activate
+    --no-default-features      Do not activate the `default` feature
+    --no-deps                  Only describe the package itself, without
+                               resolving its dependencies
+    --format-version VERSION   Version of the output format to use
+    --manifest-path PATH       Path to the manifest
+    -v, --verbose              Use verbose output
+
+The output is a JSON document, see `src/doc/metadata.md` for its format. The
+only format version currently available, and the default, is 1.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<ExportInfo>> {
+    debug!("executing; cmd=cargo-metadata; args={:?}", env::args().collect::<Vec<_>>());
+    config.shell().set_verbose(options.flag_verbose);
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+
+    let opts = ops::OutputMetadataOptions {
+        config: config,
+        features: &options.flag_features,
+        no_default_features: options.flag_no_default_features,
+        no_deps: options.flag_no_deps,
+        version: options.flag_format_version.unwrap_or(ops::METADATA_VERSION),
+    };
+
+    ops::output_metadata(&root, &opts).map(Some).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    })
+}
@@ -14,6 +14,7 @@ struct Options {
     flag_manifest_path: Option<String>,
     flag_verbose: bool,
     flag_release: bool,
+    flag_profile: Option<String>,
     arg_args: Vec<String>,
 }
 
@@ -29,6 +30,7 @@ Options:
     --example NAME          Name of the example target to run
     -j N, --jobs N          The number of jobs to run in parallel
     --release               Build artifacts in release mode, with optimizations
+    --profile NAME          Build artifacts with the custom profile NAME
     --features FEATURES     Space-separated list of features to also build
     --no-default-features   Do not build the `default` feature
     --target TRIPLE         Build for the target triple
@@ -47,6 +49,11 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     config.shell().set_verbose(options.flag_verbose);
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
 
+    if options.flag_release && options.flag_profile.is_some() {
+        return Err(CliError::from_boxed(
+            human("specify either `--release` or `--profile`, not both"), 1))
+    }
+
     let env = match (options.flag_release, options.flag_example.is_some()) {
         (true, _) => "release",
         (false, true) => "test",
@@ -61,8 +68,12 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         dev_deps: true,
         features: &options.flag_features,
         no_default_features: options.flag_no_default_features,
-        spec: None,
+        spec: &[],
+        all_members: false,
         lib_only: false,
+        bin_only: None,
+        target_rustc_args: None,
+        profile: options.flag_profile.as_ref().map(|s| &s[..]),
         exec_engine: None,
     };
 
@@ -0,0 +1,95 @@
+use std::env;
+use std::slice;
+
+use cargo::ops::CompileOptions;
+use cargo::ops;
+use cargo::util::important_paths::{find_root_manifest_for_cwd};
+use cargo::util::{CliResult, CliError, human, Config};
+
+#[derive(RustcDecodable)]
+struct Options {
+    arg_opts: Vec<String>,
+    flag_package: Option<String>,
+    flag_jobs: Option<u32>,
+    flag_features: Vec<String>,
+    flag_no_default_features: bool,
+    flag_target: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: bool,
+    flag_release: bool,
+    flag_profile: Option<String>,
+    flag_lib: bool,
+    flag_bin: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Compile a package and all of its dependencies, passing extra options to rustc
+
+Usage:
+    cargo rustc [options] [--] [<opts>...]
+
+Options:
+    -h, --help               Print this message
+    -p SPEC, --package SPEC  The package to build
+    -j N, --jobs N           The number of jobs to run in parallel
+    --lib                    Build only this package's library
+    --bin NAME               Build only the specified binary
+    --release                Build artifacts in release mode, with optimizations
+    --profile NAME           Build artifacts with the custom profile NAME
+    --features FEATURES      Space-separated list of features to also build
+    --no-default-features    Do not build the `default` feature
+    --target TRIPLE          Build for the target triple
+    --manifest-path PATH     Path to the manifest to compile
+    -v, --verbose            Use verbose output
+
+The specified target for the current package (or package specified by SPEC if
+provided) will be compiled along with all of its dependencies. The specified
+<opts>... will all be passed to the final compiler invocation, not any of the
+dependencies. Note that the compiler will still unconditionally receive
+arguments such as -L, --extern, and --crate-type, and the specified <opts>...
+will simply be added to the compiler invocation.
+
+This command requires that only one target is being compiled. If more than one
+target is available for the current package the filters of --lib and --bin
+must be used to select which target is compiled.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    debug!("executing; cmd=cargo-rustc; args={:?}", env::args().collect::<Vec<_>>());
+    config.shell().set_verbose(options.flag_verbose);
+
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+
+    if options.flag_release && options.flag_profile.is_some() {
+        return Err(CliError::from_boxed(
+            human("specify either `--release` or `--profile`, not both"), 1))
+    }
+
+    let env = if options.flag_release {
+        "release"
+    } else {
+        "compile"
+    };
+
+    let opts = CompileOptions {
+        env: env,
+        config: config,
+        jobs: options.flag_jobs,
+        target: options.flag_target.as_ref().map(|t| &t[..]),
+        dev_deps: false,
+        features: &options.flag_features,
+        no_default_features: options.flag_no_default_features,
+        spec: options.flag_package.as_ref().map(slice::ref_slice)
+                     .unwrap_or(&[]),
+        all_members: false,
+        lib_only: options.flag_lib,
+        bin_only: options.flag_bin.as_ref().map(|s| &s[..]),
+        target_rustc_args: Some(&options.arg_opts[..]),
+        profile: options.flag_profile.as_ref().map(|s| &s[..]),
+        exec_engine: None,
+    };
+
+    ops::compile(&root, &opts).map(|_| None).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    })
+}
@@ -11,7 +11,8 @@ struct Options {
     flag_test: Option<String>,
     flag_no_default_features: bool,
     flag_no_run: bool,
-    flag_package: Option<String>,
+    flag_package: Vec<String>,
+    flag_all: bool,
     flag_target: Option<String>,
     flag_verbose: bool,
 }
@@ -23,16 +24,17 @@ Usage:
     cargo test [options] [--] [<args>...]
 
 Options:
-    -h, --help               Print this message
-    --test NAME              Name of the test executable to run
-    --no-run                 Compile, but don't run tests
-    -p SPEC, --package SPEC  Package to run tests for
-    -j N, --jobs N           The number of jobs to run in parallel
-    --features FEATURES      Space-separated list of features to also build
-    --no-default-features    Do not build the `default` feature
-    --target TRIPLE          Build for the target triple
-    --manifest-path PATH     Path to the manifest to build tests for
-    -v, --verbose            Use verbose output
+    -h, --help                   Print this message
+    --test NAME                  Name of the test executable to run
+    --no-run                     Compile, but don't run tests
+    -p SPEC, --package SPEC ...  Package to run tests for
+    --all                        Test all packages in the workspace
+    -j N, --jobs N               The number of jobs to run in parallel
+    --features FEATURES          Space-separated list of features to also build
+    --no-default-features        Do not build the `default` feature
+    --target TRIPLE              Build for the target triple
+    --manifest-path PATH         Path to the manifest to build tests for
+    -v, --verbose                Use verbose output
 
 All of the trailing arguments are passed to the test binaries generated for
 filtering tests and generally providing options configuring how they run. For
@@ -42,8 +44,9 @@ example, this will run all tests with the name `foo` in their name:
 
 If the --package argument is given, then SPEC is a package id specification
 which indicates which package should be tested. If it is not given, then the
-current package is tested. For more information on SPEC and its format, see the
-`cargo help pkgid` command.
+current package is tested. The argument may be given several times to test
+several packages, and --all tests every member of the workspace. For more
+information on SPEC and its format, see the `cargo help pkgid` command.
 
 Compilation can be configured via the `test` profile in the manifest.
 ";
@@ -63,8 +66,12 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
             dev_deps: true,
             features: &options.flag_features,
             no_default_features: options.flag_no_default_features,
-            spec: options.flag_package.as_ref().map(|s| &s[..]),
+            spec: &options.flag_package,
+            all_members: options.flag_all,
             lib_only: false,
+            bin_only: None,
+            target_rustc_args: None,
+            profile: None,
             exec_engine: None,
         },
     };
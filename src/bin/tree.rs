@@ -0,0 +1,78 @@
+use std::env;
+
+use cargo::ops;
+use cargo::util::{CliResult, CliError, Config};
+use cargo::util::important_paths::find_root_manifest_for_cwd;
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_features: Vec<String>,
+    flag_no_default_features: bool,
+    flag_invert: Option<String>,
+    flag_duplicates: bool,
+    flag_format: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: bool,
+}
+
+pub const USAGE: &'static str = "
+Display the dependency graph of a package as a tree
+
+Usage:
+    cargo tree [options]
+
+Options:
+    -h, --help               Print this message
+    --features FEATURES      Space-separated list of features to activate
+    --no-default-features    Do not activate the `default` feature
+    -i SPEC, --invert SPEC   Show the packages depending on SPEC instead
+    -d, --duplicates         Show the packages present at several
+                             semver-incompatible versions
+    --format FORMAT          Template used to print each package
+    --manifest-path PATH     Path to the manifest of the package
+    -v, --verbose            Use verbose output
+
+This command requires that a `Cargo.lock` already exists as generated by
+`cargo build` or related commands. Dependencies listed in the
+[build-dependencies] or [dev-dependencies] sections of a package are shown
+below a line naming the section.
+
+With --invert, SPEC is a package id specification (see `cargo help pkgid`) and
+the tree lists the packages which depend on it, all the way up to the root
+package. With --duplicates such an inverted tree is shown for every version of
+the packages which are pulled in at several semver-incompatible versions.
+
+A package whose dependencies were already shown further up is marked with
+`(*)` instead of being expanded again.
+
+The FORMAT template defaults to `{p} {f}` and may contain the following
+placeholders:
+
+    {p}    the package name, version and source
+    {n}    the package name
+    {v}    the package version
+    {s}    the package source
+    {f}    the features enabled for the package, separated by commas
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    debug!("executing; cmd=cargo-tree; args={:?}", env::args().collect::<Vec<_>>());
+    config.shell().set_verbose(options.flag_verbose);
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+
+    let format = options.flag_format.as_ref().map(|s| &s[..]);
+    let opts = ops::TreeOptions {
+        config: config,
+        features: &options.flag_features,
+        no_default_features: options.flag_no_default_features,
+        invert: options.flag_invert.as_ref().map(|s| &s[..]),
+        duplicates: options.flag_duplicates,
+        format: format.unwrap_or("{p} {f}"),
+    };
+
+    let tree = try!(ops::tree(&root, &opts).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    }));
+    print!("{}", tree);
+    Ok(None)
+}
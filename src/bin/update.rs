@@ -11,6 +11,7 @@ struct Options {
     flag_precise: Option<String>,
     flag_manifest_path: Option<String>,
     flag_verbose: bool,
+    flag_minimal_versions: bool,
 }
 
 pub const USAGE: &'static str = "
@@ -25,6 +26,7 @@ Options:
     --aggressive             Force updating all dependencies of <name> as well
     --precise PRECISE        Update a single dependency to exactly PRECISE
     --manifest-path PATH     Path to the manifest to compile
+    --minimal-versions       Pick the lowest version matching each requirement
     -v, --verbose            Use verbose output
 
 This command requires that a `Cargo.lock` already exists as generated by
@@ -45,6 +47,9 @@ be updated to.
 If SPEC is not given, then all dependencies will be re-resolved and
 updated.
 
+If --minimal-versions is given, the dependencies being updated are resolved to
+the oldest versions their requirements allow rather than the newest ones.
+
 For more information about package id specifications, see `cargo help pkgid`.
 ";
 
@@ -57,6 +62,7 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
 
     let update_opts = ops::UpdateOptions {
         aggressive: options.flag_aggressive,
+        minimal_versions: options.flag_minimal_versions,
         precise: options.flag_precise.as_ref().map(|s| &s[..]),
         to_update: spec.map(|s| &s[..]),
         config: config,
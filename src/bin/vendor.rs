@@ -0,0 +1,53 @@
+use std::env;
+use std::path::Path;
+
+use cargo::ops;
+use cargo::util::{CliResult, CliError, Config};
+use cargo::util::important_paths::find_root_manifest_for_cwd;
+
+#[derive(RustcDecodable)]
+struct Options {
+    arg_path: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: bool,
+}
+
+pub const USAGE: &'static str = "
+Vendor all dependencies of a package into a local directory
+
+Usage:
+    cargo vendor [options] [<path>]
+
+Options:
+    -h, --help              Print this message
+    --manifest-path PATH    Path to the manifest of the package
+    -v, --verbose           Use verbose output
+
+Every registry and git package the dependency graph contains is copied into
+its own `<name>-<version>` directory below <path>, which defaults to `vendor`
+next to the manifest. A `.cargo-checksum.json` file listing the sha256 of each
+copied file is written into every one of these directories. Directories left
+by a previous run for packages which are no longer depended upon are removed.
+
+Once done, the `.cargo/config` snippet which makes later builds use the
+vendored copies instead of the network is printed. It is meant to be added to
+`.cargo/config` next to the manifest.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    debug!("executing; cmd=cargo-vendor; args={:?}", env::args().collect::<Vec<_>>());
+    config.shell().set_verbose(options.flag_verbose);
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+
+    let path = options.arg_path.as_ref().map(|s| &s[..]).unwrap_or("vendor");
+    let opts = ops::VendorOptions {
+        config: config,
+        destination: Path::new(path),
+    };
+
+    let snippet = try!(ops::vendor(&root, &opts).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    }));
+    print!("{}", snippet);
+    Ok(None)
+}
@@ -1,10 +1,11 @@
+use std::collections::HashMap;
 use std::hash;
 use std::path::{PathBuf, Path};
 
 use semver::Version;
 use rustc_serialize::{Encoder,Encodable};
 
-use core::{Dependency, PackageId, Summary};
+use core::{Dependency, PackageId, PackageIdSpec, Summary};
 use core::package_id::Metadata;
 use core::dependency::SerializedDependency;
 use util::{CargoResult, human};
@@ -21,6 +22,9 @@ pub struct Manifest {
     exclude: Vec<String>,
     include: Vec<String>,
     metadata: ManifestMetadata,
+    replace: Vec<(PackageIdSpec, Dependency)>,
+    workspace: Option<Vec<PathBuf>>,
+    profiles: Profiles,
 }
 
 /// General metadata about a package which is just blindly uploaded to the
@@ -265,6 +269,76 @@ impl Profile {
         self.custom_build = custom_build;
         self
     }
+
+    /// Sets the directory of the target directory the artifacts are placed in.
+    pub fn set_dest(mut self, dest: &str) -> Profile {
+        self.dest = dest.to_string();
+        self
+    }
+}
+
+/// Settings of a `[profile.*]` section applied on top of the profile of a
+/// target, where `None` keeps the setting of the target's profile.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct ProfileOverride {
+    pub opt_level: Option<u32>,
+    pub lto: Option<bool>,
+    pub codegen_units: Option<u32>,
+    pub debug: Option<bool>,
+    pub rpath: Option<bool>,
+}
+
+impl ProfileOverride {
+    pub fn apply(&self, profile: Profile) -> Profile {
+        let opt_level = self.opt_level.unwrap_or(profile.opt_level());
+        let lto = self.lto.unwrap_or(profile.lto());
+        let codegen_units = self.codegen_units.or(profile.codegen_units());
+        let debug = self.debug.unwrap_or(profile.debug());
+        let rpath = self.rpath.unwrap_or(profile.rpath());
+        profile.set_opt_level(opt_level).set_lto(lto)
+               .set_codegen_units(codegen_units)
+               .set_debug(debug).set_rpath(rpath)
+    }
+}
+
+/// A profile defined by a manifest in addition to the built-in ones.
+///
+/// It applies to the targets of the built-in profile it inherits from, and its
+/// artifacts are placed in a directory of the target directory named after it.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct CustomProfile {
+    /// The environment of the inherited profile, `compile` or `release`.
+    pub env: String,
+    pub settings: ProfileOverride,
+}
+
+/// The parts of the `[profile]` section of a manifest which aren't recorded in
+/// the profiles of its targets, and only apply when it is the root of a build.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Profiles {
+    custom: HashMap<String, CustomProfile>,
+    // Keyed by the environment of a built-in profile or the name of a custom
+    // one, then by the name of the package.
+    overrides: HashMap<(String, String), ProfileOverride>,
+}
+
+impl Profiles {
+    pub fn new(custom: HashMap<String, CustomProfile>,
+               overrides: HashMap<(String, String), ProfileOverride>)
+               -> Profiles {
+        Profiles { custom: custom, overrides: overrides }
+    }
+
+    pub fn custom(&self, name: &str) -> Option<&CustomProfile> {
+        self.custom.get(name)
+    }
+
+    /// The override for the package named `package` when built for the
+    /// profile `env`.
+    pub fn package_override(&self, env: &str, package: &str)
+                            -> Option<&ProfileOverride> {
+        self.overrides.get(&(env.to_string(), package.to_string()))
+    }
 }
 
 impl hash::Hash for Profile {
@@ -342,7 +416,10 @@ impl Manifest {
                exclude: Vec<String>,
                include: Vec<String>,
                links: Option<String>,
-               metadata: ManifestMetadata) -> Manifest {
+               metadata: ManifestMetadata,
+               replace: Vec<(PackageIdSpec, Dependency)>,
+               workspace: Option<Vec<PathBuf>>,
+               profiles: Profiles) -> Manifest {
         Manifest {
             summary: summary,
             targets: targets,
@@ -353,6 +430,9 @@ impl Manifest {
             include: include,
             links: links,
             metadata: metadata,
+            replace: replace,
+            workspace: workspace,
+            profiles: profiles,
         }
     }
 
@@ -363,11 +443,18 @@ impl Manifest {
     pub fn metadata(&self) -> &ManifestMetadata { &self.metadata }
     pub fn name(&self) -> &str { self.package_id().name() }
     pub fn package_id(&self) -> &PackageId { self.summary.package_id() }
+    pub fn profiles(&self) -> &Profiles { &self.profiles }
+    pub fn replace(&self) -> &[(PackageIdSpec, Dependency)] { &self.replace }
     pub fn summary(&self) -> &Summary { &self.summary }
     pub fn target_dir(&self) -> &Path { &self.target_dir }
     pub fn targets(&self) -> &[Target] { &self.targets }
     pub fn version(&self) -> &Version { self.package_id().version() }
     pub fn warnings(&self) -> &[String] { &self.warnings }
+    /// The paths of the members listed in the `[workspace]` table, relative to
+    /// the package, if this package is the root of a workspace.
+    pub fn workspace_members(&self) -> Option<&[PathBuf]> {
+        self.workspace.as_ref().map(|v| &v[..])
+    }
     pub fn links(&self) -> Option<&str> {
         self.links.as_ref().map(|s| s.as_slice())
     }
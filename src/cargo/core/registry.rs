@@ -2,7 +2,10 @@ use std::collections::HashSet;
 use std::collections::hash_map::HashMap;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 
+use semver::VersionReq;
+
 use core::{Source, SourceId, SourceMap, Summary, Dependency, PackageId, Package};
+use core::PackageIdSpec;
 use util::{CargoResult, ChainError, Config, human, profile};
 
 /// Source of informations about a group of packages.
@@ -41,6 +44,10 @@ pub struct PackageRegistry<'a, 'b: 'a> {
     // when querying for packages.
     overrides: Vec<SourceId>,
 
+    // The `[replace]` entries of the root manifest. Any package matching one of
+    // the specs is taken from the source of the paired dependency instead.
+    replacements: Vec<(PackageIdSpec, Dependency)>,
+
     // Note that each SourceId does not take into account its `precise` field
     // when hashing or testing for equality. When adding a new `SourceId`, we
     // want to avoid duplicates in the `SourceMap` (to prevent re-updating the
@@ -74,6 +81,7 @@ impl<'a, 'b> PackageRegistry<'a, 'b> {
             sources: SourceMap::new(),
             source_ids: HashMap::new(),
             overrides: vec!(),
+            replacements: Vec::new(),
             config: config,
             locked: HashMap::new(),
         }
@@ -147,6 +155,15 @@ impl<'a, 'b> PackageRegistry<'a, 'b> {
         Ok(())
     }
 
+    pub fn add_replacements(&mut self,
+                            replacements: &[(PackageIdSpec, Dependency)]) {
+        for replacement in replacements.iter() {
+            if !self.replacements.contains(replacement) {
+                self.replacements.push(replacement.clone());
+            }
+        }
+    }
+
     pub fn register_lock(&mut self, id: PackageId, deps: Vec<PackageId>) {
         let sub_map = match self.locked.entry(id.source_id().clone()) {
             Occupied(e) => e.into_mut(),
@@ -194,6 +211,41 @@ impl<'a, 'b> PackageRegistry<'a, 'b> {
         Ok(ret)
     }
 
+    // Swaps a summary for the package of the same name and version from the
+    // source given in a matching `[replace]` entry.
+    //
+    // If the replacement was locked previously, the source is loaded at the
+    // locked revision so that the lockfile keeps being honored.
+    fn replace(&mut self, summary: Summary) -> CargoResult<Summary> {
+        let dep = match self.replacements.iter().find(|&&(ref spec, ref dep)| {
+            spec.matches(summary.package_id()) &&
+                dep.source_id() != summary.source_id()
+        }) {
+            Some(&(_, ref dep)) => dep.clone(),
+            None => return Ok(summary),
+        };
+        let dep = dep.set_version_req(VersionReq::exact(summary.version()));
+
+        let locked = self.locked.get(dep.source_id()).and_then(|map| {
+            map.get(dep.name())
+        }).and_then(|vec| {
+            vec.iter().find(|&&(ref id, _)| dep.matches_id(id))
+        }).map(|&(ref id, _)| id.source_id().clone());
+        let source_id = locked.unwrap_or_else(|| dep.source_id().clone());
+        try!(self.ensure_loaded(&source_id));
+
+        let mut summaries = try!(self.sources.get_mut(&source_id).unwrap()
+                                     .query(&dep));
+        if summaries.len() == 0 {
+            return Err(human(format!("the replacement for `{}` does not \
+                                      provide version {} of `{}`\n\
+                                      location searched: {}",
+                                     summary.package_id(), summary.version(),
+                                     summary.name(), dep.source_id())))
+        }
+        Ok(summaries.remove(0))
+    }
+
     // This function is used to transform a summary to another locked summary if
     // possible. This is where the the concept of a lockfile comes into play.
     //
@@ -290,9 +342,15 @@ impl<'a, 'b> Registry for PackageRegistry<'a, 'b> {
             overrides
         };
 
-        // post-process all returned summaries to ensure that we lock all
-        // relevant summaries to the right versions and sources
-        Ok(ret.into_iter().map(|summary| self.lock(summary)).collect())
+        // post-process all returned summaries to ensure that we apply the
+        // replacements and lock all relevant summaries to the right versions
+        // and sources
+        let mut summaries = Vec::new();
+        for summary in ret.into_iter() {
+            let summary = try!(self.replace(summary));
+            summaries.push(self.lock(summary));
+        }
+        Ok(summaries)
     }
 }
 
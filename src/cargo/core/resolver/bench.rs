@@ -0,0 +1,126 @@
+//! Benchmarks of the resolver on large dependency graphs.
+
+use std::collections::HashMap;
+
+use test::Bencher;
+
+use core::{Dependency, PackageId, SourceId, Summary};
+use core::registry::test::RegistryBuilder;
+use util::ToUrl;
+use super::{resolve, Method};
+
+fn registry_loc() -> SourceId {
+    SourceId::for_registry(&"http://example.com".to_url().unwrap())
+}
+
+fn summary(name: &str, version: &str, deps: &[(String, &str)]) -> Summary {
+    let source = registry_loc();
+    let deps = deps.iter().map(|&(ref name, req)| {
+        Dependency::parse(name, Some(req), &source).unwrap()
+    }).collect();
+    let pkgid = PackageId::new(name, version, &source).unwrap();
+    Summary::new(pkgid, deps, HashMap::new()).unwrap()
+}
+
+// A chain of packages, each depending on the next one.
+#[bench]
+fn deep_chain(b: &mut Bencher) {
+    let n = 2000;
+    let summaries = (0..n).map(|i| {
+        let deps = if i + 1 < n {
+            vec![(format!("p{}", i + 1), "*")]
+        } else {
+            Vec::new()
+        };
+        summary(&format!("p{}", i), "1.0.0", &deps)
+    }).collect::<Vec<_>>();
+    let root = summary("root", "1.0.0", &[("p0".to_string(), "*")]);
+
+    b.iter(|| {
+        let mut reg = RegistryBuilder::new().summaries(summaries.clone());
+        resolve(&root, Method::Everything, &mut reg).unwrap()
+    });
+}
+
+// Layers of packages available in a few compatible versions each, with every
+// package depending on a few packages of the next layer.
+#[bench]
+fn layered_graph(b: &mut Bencher) {
+    let (layers, width) = (10, 300);
+    let mut summaries = Vec::new();
+    for layer in 0..layers {
+        for i in 0..width {
+            let deps = if layer + 1 < layers {
+                (0..3).map(|j| {
+                    (format!("l{}-{}", layer + 1, (i * 7 + j * 13) % width), "^1")
+                }).collect()
+            } else {
+                Vec::new()
+            };
+            for version in ["1.0.0", "1.1.0", "1.2.0"].iter() {
+                summaries.push(summary(&format!("l{}-{}", layer, i), version,
+                                       &deps));
+            }
+        }
+    }
+    let deps = (0..width).map(|i| (format!("l0-{}", i), "^1"))
+                         .collect::<Vec<_>>();
+    let root = summary("root", "1.0.0", &deps);
+
+    b.iter(|| {
+        let mut reg = RegistryBuilder::new().summaries(summaries.clone());
+        resolve(&root, Method::Everything, &mut reg).unwrap()
+    });
+}
+
+// Many dependencies available in several compatible versions, where only the
+// last dependency resolved reveals that the first one has to be downgraded.
+// Backtracking chronologically tries every combination of the dependencies in
+// between.
+#[bench]
+fn backjump_over_unrelated_choices(b: &mut Bencher) {
+    let n = 1000;
+    let mut summaries = Vec::new();
+    let mut deps = Vec::new();
+    for i in 0..n {
+        for version in ["1.0.0", "1.1.0", "1.2.0"].iter() {
+            summaries.push(summary(&format!("x{}", i), version, &[]));
+        }
+        deps.push((format!("x{}", i), "*"));
+    }
+    for version in ["1.0.0", "1.1.0", "1.2.0", "1.3.0", "1.4.0"].iter() {
+        summaries.push(summary("z", version, &[("x0".to_string(), "=1.0.0")]));
+    }
+    deps.push(("z".to_string(), "*"));
+    let root = summary("root", "1.0.0", &deps);
+
+    b.iter(|| {
+        let mut reg = RegistryBuilder::new().summaries(summaries.clone());
+        resolve(&root, Method::Everything, &mut reg).unwrap()
+    });
+}
+
+// Like `backjump_over_unrelated_choices`, but the graph can't be resolved at
+// all, which has to be found out without trying every combination.
+#[bench]
+fn unresolvable_after_many_choices(b: &mut Bencher) {
+    let n = 1000;
+    let mut summaries = Vec::new();
+    let mut deps = Vec::new();
+    for i in 0..n {
+        for version in ["1.0.0", "2.0.0", "3.0.0"].iter() {
+            summaries.push(summary(&format!("x{}", i), version, &[]));
+        }
+        deps.push((format!("x{}", i), "*"));
+    }
+    for version in ["1.0.0", "1.1.0", "1.2.0", "1.3.0", "1.4.0"].iter() {
+        summaries.push(summary("y", version, &[("missing".to_string(), "*")]));
+    }
+    deps.push(("y".to_string(), "*"));
+    let root = summary("root", "1.0.0", &deps);
+
+    b.iter(|| {
+        let mut reg = RegistryBuilder::new().summaries(summaries.clone());
+        assert!(resolve(&root, Method::Everything, &mut reg).is_err())
+    });
+}
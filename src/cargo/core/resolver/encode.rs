@@ -4,7 +4,7 @@ use regex::Regex;
 use rustc_serialize::{Encodable, Encoder, Decodable, Decoder};
 
 use core::{PackageId, SourceId};
-use util::{CargoResult, Graph};
+use util::{CargoResult, ChainError, Graph, human};
 
 use super::Resolve;
 
@@ -17,6 +17,10 @@ pub struct EncodableResolve {
 
 pub type Metadata = BTreeMap<String, String>;
 
+/// Checksums are stored in the `[metadata]` table under keys of the form
+/// `checksum <name> <version> (<source>)`.
+const CHECKSUM_PREFIX: &'static str = "checksum ";
+
 impl EncodableResolve {
     pub fn to_resolve(&self, default: &SourceId) -> CargoResult<Resolve> {
         let mut g = Graph::new();
@@ -69,11 +73,28 @@ impl EncodableResolve {
             }
         }
 
+        let mut checksums = HashMap::new();
+        let mut metadata = self.metadata.clone();
+        if let Some(ref mut metadata) = metadata {
+            let keys = metadata.keys().filter(|k| {
+                k.starts_with(CHECKSUM_PREFIX)
+            }).cloned().collect::<Vec<_>>();
+            for key in keys.into_iter() {
+                let checksum = metadata.remove(&key).unwrap();
+                let id = try!(EncodablePackageId::parse(&key[CHECKSUM_PREFIX.len()..])
+                                                 .chain_error(|| {
+                    human(format!("invalid checksum key in lockfile: `{}`", key))
+                }));
+                checksums.insert(try!(id.to_package_id(default)), checksum);
+            }
+        }
+
         Ok(Resolve {
             graph: g,
             root: try!(self.root.to_package_id(default)),
             features: HashMap::new(),
-            metadata: self.metadata.clone(),
+            checksums: checksums,
+            metadata: metadata,
         })
     }
 }
@@ -104,20 +125,27 @@ pub struct EncodablePackageId {
 
 impl Encodable for EncodablePackageId {
     fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
-        let mut out = format!("{} {}", self.name, self.version);
-        if let Some(ref s) = self.source {
-            out.push_str(&format!(" ({})", s.to_url()));
-        }
-        out.encode(s)
+        self.to_string().encode(s)
     }
 }
 
 impl Decodable for EncodablePackageId {
     fn decode<D: Decoder>(d: &mut D) -> Result<EncodablePackageId, D::Error> {
         let string: String = try!(Decodable::decode(d));
+        Ok(EncodablePackageId::parse(&string)
+                              .expect("invalid serialized PackageId"))
+    }
+}
+
+impl EncodablePackageId {
+    /// Parses the `<name> <version> (<source>)` form a package id is
+    /// serialized to.
+    fn parse(string: &str) -> Option<EncodablePackageId> {
         let regex = Regex::new(r"^([^ ]+) ([^ ]+)(?: \(([^\)]+)\))?$").unwrap();
-        let captures = regex.captures(&string)
-                            .expect("invalid serialized PackageId");
+        let captures = match regex.captures(string) {
+            Some(captures) => captures,
+            None => return None,
+        };
 
         let name = captures.at(1).unwrap();
         let version = captures.at(2).unwrap();
@@ -126,15 +154,21 @@ impl Decodable for EncodablePackageId {
 
         let source_id = source.map(|s| SourceId::from_url(s.to_string()));
 
-        Ok(EncodablePackageId {
+        Some(EncodablePackageId {
             name: name.to_string(),
             version: version.to_string(),
             source: source_id
         })
     }
-}
 
-impl EncodablePackageId {
+    fn to_string(&self) -> String {
+        let mut out = format!("{} {}", self.name, self.version);
+        if let Some(ref s) = self.source {
+            out.push_str(&format!(" ({})", s.to_url()));
+        }
+        out
+    }
+
     fn to_package_id(&self, default_source: &SourceId) -> CargoResult<PackageId> {
         PackageId::new(
             &self.name,
@@ -154,10 +188,24 @@ impl Encodable for Resolve {
             Some(encodable_resolve_node(id, &self.root, &self.graph))
         }).collect::<Vec<EncodableDependency>>();
 
+        let mut metadata = self.metadata.clone();
+        for &id in ids.iter() {
+            let checksum = match self.checksums.get(id) {
+                Some(checksum) => checksum,
+                None => continue,
+            };
+            let key = format!("{}{}", CHECKSUM_PREFIX,
+                              encodable_package_id(id, &self.root).to_string());
+            if metadata.is_none() {
+                metadata = Some(BTreeMap::new());
+            }
+            metadata.as_mut().unwrap().insert(key, checksum.clone());
+        }
+
         EncodableResolve {
             package: Some(encodable),
             root: encodable_resolve_node(&self.root, &self.root, &self.graph),
-            metadata: self.metadata.clone(),
+            metadata: metadata,
         }.encode(s)
     }
 }
@@ -1,14 +1,16 @@
-use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::HashSet;
 use std::collections::hash_map::HashMap;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::fmt;
+use std::iter::repeat;
 use std::rc::Rc;
+use std::slice;
 use semver;
 
 use core::{PackageId, Registry, SourceId, Summary, Dependency};
 use core::PackageIdSpec;
-use util::{CargoResult, Graph, human, ChainError, CargoError};
+use util::{CargoResult, Graph, human, ChainError};
 use util::profile;
 use util::graph::{Nodes, Edges};
 
@@ -16,6 +18,7 @@ pub use self::encode::{EncodableResolve, EncodableDependency, EncodablePackageId
 pub use self::encode::Metadata;
 
 mod encode;
+#[cfg(test)] mod bench;
 
 /// Represents a fully resolved package dependency graph. Each node in the graph
 /// is a package and edges represent dependencies between packages.
@@ -27,6 +30,7 @@ pub struct Resolve {
     graph: Graph<PackageId>,
     features: HashMap<PackageId, HashSet<String>>,
     root: PackageId,
+    checksums: HashMap<PackageId, String>,
     metadata: Option<Metadata>,
 }
 
@@ -43,7 +47,13 @@ impl Resolve {
     fn new(root: PackageId) -> Resolve {
         let mut g = Graph::new();
         g.add(root.clone(), &[]);
-        Resolve { graph: g, root: root, features: HashMap::new(), metadata: None }
+        Resolve {
+            graph: g,
+            root: root,
+            features: HashMap::new(),
+            checksums: HashMap::new(),
+            metadata: None,
+        }
     }
 
     pub fn copy_metadata(&mut self, other: &Resolve) {
@@ -112,6 +122,11 @@ impl Resolve {
     pub fn features(&self, pkg: &PackageId) -> Option<&HashSet<String>> {
         self.features.get(pkg)
     }
+
+    /// The checksum of the contents of `pkg`, as recorded in the lockfile.
+    pub fn checksum(&self, pkg: &PackageId) -> Option<&str> {
+        self.checksums.get(pkg).map(|s| &s[..])
+    }
 }
 
 impl fmt::Debug for Resolve {
@@ -122,94 +137,439 @@ impl fmt::Debug for Resolve {
 
 #[derive(Clone)]
 struct Context {
-    activations: HashMap<(String, SourceId), Vec<Rc<Summary>>>,
-    resolve: Resolve,
-    visited: Rc<RefCell<HashSet<PackageId>>>,
+    // Both maps share their values with the contexts saved for backtracking,
+    // so saving a context only copies the maps themselves.
+    activations: HashMap<(String, SourceId), Rc<Vec<Rc<Summary>>>>,
+    resolve_features: HashMap<PackageId, Rc<HashSet<String>>>,
+}
+
+impl Context {
+    fn prev_active(&self, dep: &Dependency) -> &[Rc<Summary>] {
+        let key = (dep.name().to_string(), dep.source_id().clone());
+        self.activations.get(&key).map(|v| v.as_slice()).unwrap_or(&[])
+    }
+
+    fn is_active(&self, id: &PackageId) -> bool {
+        let key = (id.name().to_string(), id.source_id().clone());
+        self.activations.get(&key).map(|v| {
+            v.iter().any(|s| s.package_id() == id)
+        }).unwrap_or(false)
+    }
+}
+
+// A dependency of an activated package along with its candidates, in the order
+// they're tried, and the features requested of it.
+type DepInfo = (Rc<Dependency>, Rc<Vec<Rc<Summary>>>, Rc<Vec<String>>);
+
+// The dependencies of an activated package, `next` being the first one which
+// still needs to be resolved.
+#[derive(Clone)]
+struct DepsFrame {
+    parent: Rc<Summary>,
+    deps: Rc<Vec<DepInfo>>,
+    next: usize,
+}
+
+// A set of activated packages which can't all be part of a resolution. `None`
+// if the reason for a failure isn't precisely known, in which case nothing can
+// be skipped while backtracking.
+type Conflict = Option<HashSet<PackageId>>;
+
+type PastConflicts = HashMap<(PackageId, String),
+                             Vec<(HashSet<PackageId>, Rc<String>)>>;
+
+// The decision to use `current` for the dependency `dep` of `parent`. The
+// decisions made so far also make up the edges of the resolved graph.
+struct BacktrackFrame {
+    parent: Rc<Summary>,
+    dep: Rc<Dependency>,
+    features: Rc<Vec<String>>,
+    candidates: Rc<Vec<Rc<Summary>>>,
+    next: usize,
+    current: Rc<Summary>,
+    // The state before the decision was made, kept only as long as there are
+    // candidates left to try.
+    backup: Option<(Context, Vec<DepsFrame>)>,
+    // Why the candidates tried so far failed, see `find_candidate`.
+    conflict: Conflict,
+}
+
+// Caches the candidates for each dependency, so that backtracking doesn't
+// query the registry over and over again.
+struct RegistryQueryer<'a> {
+    registry: &'a mut (Registry + 'a),
+    minimal_versions: bool,
+    cache: HashMap<(PackageId, String), Rc<Vec<Rc<Summary>>>>,
+}
+
+impl<'a> RegistryQueryer<'a> {
+    fn query(&mut self, parent: &PackageId, dep: &Dependency)
+             -> CargoResult<Rc<Vec<Rc<Summary>>>> {
+        let key = (parent.clone(), dep.name().to_string());
+        if let Some(candidates) = self.cache.get(&key) {
+            return Ok(candidates.clone())
+        }
+        let mut candidates = try!(self.registry.query(dep));
+        // When we attempt versions for a package, we'll want to start at the
+        // maximum version and work our way down, unless the lowest matching
+        // versions were asked for.
+        let minimal = self.minimal_versions;
+        candidates.as_mut_slice().sort_by(|a, b| {
+            if minimal {
+                a.version().cmp(b.version())
+            } else {
+                b.version().cmp(a.version())
+            }
+        });
+        let candidates = Rc::new(candidates.into_iter().map(Rc::new)
+                                           .collect::<Vec<_>>());
+        self.cache.insert(key, candidates.clone());
+        Ok(candidates)
+    }
+}
+
+/// Options altering which of the versions matching a requirement the resolver
+/// picks.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct ResolveOptions {
+    /// Prefer the lowest version satisfying each requirement instead of the
+    /// highest one. Useful to check that the lower bounds of the version
+    /// requirements of a package are actually correct.
+    pub minimal_versions: bool,
 }
 
 /// Builds the list of all packages required to build the first argument.
 pub fn resolve(summary: &Summary, method: Method,
                registry: &mut Registry) -> CargoResult<Resolve> {
-    trace!("resolve; summary={}", summary.package_id());
-    let summary = Rc::new(summary.clone());
+    resolve_with_options(summary, method, ResolveOptions::default(), registry)
+}
 
-    let cx = Box::new(Context {
-        resolve: Resolve::new(summary.package_id().clone()),
-        activations: HashMap::new(),
-        visited: Rc::new(RefCell::new(HashSet::new())),
-    });
-    let _p = profile::start(format!("resolving: {}", summary.package_id()));
-    match try!(activate(cx, registry, &summary, method)) {
-        Ok(cx) => {
-            debug!("resolved: {:?}", cx.resolve);
-            Ok(cx.resolve)
-        }
-        Err(e) => Err(e),
-    }
+/// Same as `resolve`, but with control over how candidates are picked.
+pub fn resolve_with_options(summary: &Summary, method: Method,
+                            opts: ResolveOptions,
+                            registry: &mut Registry) -> CargoResult<Resolve> {
+    resolve_roots(slice::ref_slice(summary), method, opts, registry)
 }
 
-fn activate(mut cx: Box<Context>,
-            registry: &mut Registry,
-            parent: &Rc<Summary>,
-            method: Method)
-            -> CargoResult<CargoResult<Box<Context>>> {
-    // Dependency graphs are required to be a DAG, so we keep a set of
-    // packages we're visiting and bail if we hit a dupe.
-    let id = parent.package_id();
-    if !cx.visited.borrow_mut().insert(id.clone()) {
-        return Err(human(format!("cyclic package dependency: package `{}` \
-                                  depends on itself", id)))
-    }
+/// Same as `resolve_with_options`, but resolves the dependencies of several
+/// packages into one graph, such as all members of a workspace. The first
+/// summary is the root of the graph, and `method` applies to all of them.
+pub fn resolve_roots(summaries: &[Summary], method: Method,
+                     opts: ResolveOptions,
+                     registry: &mut Registry) -> CargoResult<Resolve> {
+    let summary = &summaries[0];
+    trace!("resolve; summary={}", summary.package_id());
+    let _p = profile::start(format!("resolving: {}", summary.package_id()));
 
-    // If we're already activated, then that was easy!
-    if flag_activated(&mut *cx, parent, &method) {
-        cx.visited.borrow_mut().remove(id);
-        return Ok(Ok(cx))
+    let mut registry = RegistryQueryer {
+        registry: registry,
+        minimal_versions: opts.minimal_versions,
+        cache: HashMap::new(),
+    };
+    let roots = summaries.iter().map(|s| Rc::new(s.clone())).collect();
+    let (cx, decisions) = try!(activate_deps_loop(&mut registry, roots,
+                                                  method));
+    try!(check_cycles(&decisions));
+
+    let mut resolve = Resolve::new(summary.package_id().clone());
+    for summaries in cx.activations.values() {
+        for summary in summaries.iter() {
+            let id = summary.package_id();
+            resolve.graph.add(id.clone(), &[]);
+            if let Some(checksum) = summary.checksum() {
+                resolve.checksums.insert(id.clone(), checksum.to_string());
+            }
+        }
+    }
+    for frame in decisions.iter() {
+        resolve.graph.link(frame.parent.package_id().clone(),
+                           frame.current.package_id().clone());
     }
-    debug!("activating {}", parent.package_id());
+    resolve.features = cx.resolve_features.into_iter().map(|(id, features)| {
+        (id, (*features).clone())
+    }).collect();
+    debug!("resolved: {:?}", resolve);
+    Ok(resolve)
+}
 
+// Resolves the dependencies of `roots` depth first, always resolving the
+// dependencies of the most recently activated package first.
+//
+// Whenever a dependency can't be resolved we go back to the most recent
+// decision which can possibly avoid the conflict, see `find_candidate`.
+// Returns the final context along with all decisions that led to it.
+fn activate_deps_loop(registry: &mut RegistryQueryer,
+                      roots: Vec<Rc<Summary>>,
+                      method: Method)
+                      -> CargoResult<(Context, Vec<BacktrackFrame>)> {
     // Extracting the platform request.
     let platform = match method {
         Method::Required{target_platform: platform, ..} => platform,
         Method::Everything => None,
     };
 
+    let mut cx = Context {
+        activations: HashMap::new(),
+        resolve_features: HashMap::new(),
+    };
+    let mut remaining_deps = Vec::new();
+    let mut decisions: Vec<BacktrackFrame> = Vec::new();
+    let mut past_conflicts: PastConflicts = HashMap::new();
+
+    for root in roots.iter() {
+        if let Some(frame) = try!(activate(&mut cx, registry, root, method)) {
+            remaining_deps.push(frame);
+        }
+    }
+
+    loop {
+        let next = match remaining_deps.last_mut() {
+            Some(frame) => {
+                if frame.next < frame.deps.len() {
+                    frame.next += 1;
+                    Some((frame.parent.clone(), frame.deps[frame.next - 1].clone()))
+                } else {
+                    None
+                }
+            }
+            None => break,
+        };
+        let (parent, (dep, candidates, features)) = match next {
+            Some(next) => next,
+            None => { remaining_deps.pop(); continue }
+        };
+        trace!("{}>{} {} candidates", parent.name(), dep.name(),
+               candidates.len());
+
+        // If this dependency already failed with everything that's active
+        // right now, there's no need to try again.
+        let key = (parent.package_id().clone(), dep.name().to_string());
+        let known = past_conflicts.get(&key).and_then(|conflicts| {
+            conflicts.iter().find(|&&(ref set, _)| {
+                set.iter().all(|id| cx.is_active(id))
+            })
+        }).map(|&(ref set, ref msg)| (Some(set.clone()), msg.clone()));
+
+        let (conflict, msg) = match known {
+            Some(known) => known,
+            None => {
+                let mut blockers = HashSet::new();
+                let mut next = 0;
+                let candidate = next_candidate(&cx, &dep, &candidates,
+                                               &mut next, &mut blockers);
+                blockers.insert(parent.package_id().clone());
+                // An optional dependency is only needed because of the
+                // features enabled so far, which isn't tracked in conflicts.
+                let conflict = if dep.is_optional() {None} else {Some(blockers)};
+
+                match candidate {
+                    Some(candidate) => {
+                        let backup = if next < candidates.len() {
+                            Some((cx.clone(), remaining_deps.clone()))
+                        } else {
+                            None
+                        };
+                        decisions.push(BacktrackFrame {
+                            parent: parent.clone(),
+                            dep: dep.clone(),
+                            features: features.clone(),
+                            candidates: candidates.clone(),
+                            next: next,
+                            current: candidate.clone(),
+                            backup: backup,
+                            conflict: conflict,
+                        });
+                        let method = Method::Required{
+                            dev_deps: false,
+                            features: &features[..],
+                            uses_default_features: dep.uses_default_features(),
+                            target_platform: platform};
+                        if let Some(frame) = try!(activate(&mut cx, registry,
+                                                           &candidate, method)) {
+                            remaining_deps.push(frame);
+                        }
+                        continue
+                    }
+                    None => {
+                        let msg = try!(activation_error(&cx, registry,
+                                                        &decisions, &parent,
+                                                        &dep, &candidates));
+                        (conflict, Rc::new(msg))
+                    }
+                }
+            }
+        };
+
+        trace!("{}>{} -- conflict: {:?}", parent.name(), dep.name(), conflict);
+        match find_candidate(&mut decisions, conflict, &mut past_conflicts,
+                             &msg) {
+            Some((candidate, (backup_cx, backup_deps))) => {
+                cx = backup_cx;
+                remaining_deps = backup_deps;
+                let frame = decisions.last().unwrap();
+                let method = Method::Required{
+                    dev_deps: false,
+                    features: &frame.features[..],
+                    uses_default_features: frame.dep.uses_default_features(),
+                    target_platform: platform};
+                if let Some(frame) = try!(activate(&mut cx, registry,
+                                                   &candidate, method)) {
+                    remaining_deps.push(frame);
+                }
+            }
+            None => return Err(human((*msg).clone())),
+        }
+    }
+
+    Ok((cx, decisions))
+}
+
+// Activates `candidate`, returning its dependencies if they still need to be
+// resolved.
+fn activate(cx: &mut Context,
+            registry: &mut RegistryQueryer,
+            candidate: &Rc<Summary>,
+            method: Method) -> CargoResult<Option<DepsFrame>> {
+    // If we're already activated, then that was easy!
+    if flag_activated(cx, candidate, &method) {
+        return Ok(None)
+    }
+    debug!("activating {}", candidate.package_id());
+
     // First, figure out our set of dependencies based on the requsted set of
     // features. This also calculates what features we're going to enable for
     // our own dependencies.
-    let deps = try!(resolve_features(&mut cx, parent, method));
+    let deps = try!(resolve_features(cx, &**candidate, method));
 
     // Next, transform all dependencies into a list of possible candidates which
     // can satisfy that dependency.
     let mut deps = try!(deps.into_iter().map(|(_dep_name, (dep, features))| {
-        let mut candidates = try!(registry.query(dep));
-        // When we attempt versions for a package, we'll want to start at the
-        // maximum version and work our way down.
-        candidates.as_mut_slice().sort_by(|a, b| {
-            b.version().cmp(a.version())
-        });
-        let candidates = candidates.into_iter().map(Rc::new).collect::<Vec<_>>();
-        Ok((dep, candidates, features))
-    }).collect::<CargoResult<Vec<_>>>());
-
-    // When we recurse, attempt to resolve dependencies with fewer candidates
-    // before recursing on dependencies with more candidates. This way if the
-    // dependency with only one candidate can't be resolved we don't have to do
-    // a bunch of work before we figure that out.
-    deps.as_mut_slice().sort_by(|&(_, ref a, _), &(_, ref b, _)| {
-        a.len().cmp(&b.len())
+        let candidates = try!(registry.query(candidate.package_id(), dep));
+        Ok((Rc::new(dep.clone()), candidates, Rc::new(features)))
+    }).collect::<CargoResult<Vec<DepInfo>>>());
+
+    // Attempt to resolve dependencies with fewer candidates before
+    // dependencies with more candidates. This way if the dependency with only
+    // one candidate can't be resolved we don't have to do a bunch of work
+    // before we figure that out.
+    deps.as_mut_slice().sort_by(|a, b| {
+        match a.1.len().cmp(&b.1.len()) {
+            Ordering::Equal => a.0.name().cmp(b.0.name()),
+            ord => ord,
+        }
     });
 
-    // Workaround compilation error: `deps` does not live long enough
-    let platform = platform.map(|s| &*s);
+    Ok(Some(DepsFrame {
+        parent: candidate.clone(),
+        deps: Rc::new(deps),
+        next: 0,
+    }))
+}
+
+// Returns the first candidate starting at `*next` which can be used given the
+// versions already activated. We can actually use a version if it precisely
+// matches an activated version or if it is otherwise incompatible with all
+// other activated versions. Note that we define "compatible" here in terms of
+// the semver sense where if the left-most nonzero digit is the same they're
+// considered compatible.
+//
+// The activated versions ruling out the candidates skipped are added to
+// `blockers`.
+fn next_candidate(cx: &Context, dep: &Dependency, candidates: &[Rc<Summary>],
+                  next: &mut usize, blockers: &mut HashSet<PackageId>)
+                  -> Option<Rc<Summary>> {
+    let prev_active = cx.prev_active(dep);
+    while *next < candidates.len() {
+        let candidate = &candidates[*next];
+        *next += 1;
+        if prev_active.iter().any(|a| a == candidate) {
+            return Some(candidate.clone())
+        }
+        let conflicting = prev_active.iter().filter(|a| {
+            compatible(a.version(), candidate.version())
+        }).map(|a| a.package_id().clone()).collect::<Vec<_>>();
+        if conflicting.is_empty() {
+            return Some(candidate.clone())
+        }
+        blockers.extend(conflicting.into_iter());
+    }
+    None
+}
+
+// Undoes decisions until one is found whose remaining candidates may avoid
+// `conflict`. Returns the candidate to try next along with the state to
+// resume from, leaving the decision on top of `decisions`.
+//
+// A decision is skipped altogether if everything in the conflict was already
+// active before it was made, as none of its alternatives can change that.
+// Otherwise the conflict is attributed to the candidate currently used. Once
+// all candidates failed, the union of their conflicts (without the candidates
+// themselves) explains why the dependency can't be resolved at all. That is
+// remembered for later and used to keep backtracking, which jumps right back
+// to the decisions involved.
+fn find_candidate(decisions: &mut Vec<BacktrackFrame>,
+                  mut conflict: Conflict,
+                  past_conflicts: &mut PastConflicts,
+                  msg: &Rc<String>)
+                  -> Option<(Rc<Summary>, (Context, Vec<DepsFrame>))> {
+    while let Some(mut frame) = decisions.pop() {
+        let skip = match (&frame.backup, &conflict) {
+            (&Some((ref cx, _)), &Some(ref set)) => {
+                set.iter().all(|id| cx.is_active(id))
+            }
+            _ => false,
+        };
+        if skip { continue }
+
+        frame.conflict = match (frame.conflict.take(), conflict.take()) {
+            (Some(mut reasons), Some(set)) => {
+                let current = frame.current.package_id().clone();
+                reasons.extend(set.into_iter().filter(|id| *id != current));
+                Some(reasons)
+            }
+            _ => None,
+        };
 
-    Ok(match try!(activate_deps(cx, registry, parent, platform, &deps, 0)) {
-        Ok(cx) => {
-            cx.visited.borrow_mut().remove(parent.package_id());
-            Ok(cx)
+        let candidate = match frame.backup {
+            Some((ref cx, _)) => {
+                let mut blockers = HashSet::new();
+                let candidate = next_candidate(cx, &frame.dep,
+                                               &frame.candidates,
+                                               &mut frame.next,
+                                               &mut blockers);
+                if let Some(ref mut reasons) = frame.conflict {
+                    reasons.extend(blockers.into_iter());
+                }
+                candidate
+            }
+            None => None,
+        };
+
+        match candidate {
+            Some(candidate) => {
+                frame.current = candidate.clone();
+                let backup = if frame.next < frame.candidates.len() {
+                    frame.backup.clone().unwrap()
+                } else {
+                    frame.backup.take().unwrap()
+                };
+                decisions.push(frame);
+                return Some((candidate, backup))
+            }
+            None => {
+                if let Some(ref reasons) = frame.conflict {
+                    let key = (frame.parent.package_id().clone(),
+                               frame.dep.name().to_string());
+                    match past_conflicts.entry(key) {
+                        Occupied(e) => e.into_mut(),
+                        Vacant(e) => e.insert(Vec::new()),
+                    }.push((reasons.clone(), msg.clone()));
+                }
+                conflict = frame.conflict.take();
+            }
         }
-        Err(e) => Err(e),
-    })
+    }
+    None
 }
 
 // Activate this summary by inserting it into our list of known activations.
@@ -221,11 +581,12 @@ fn flag_activated(cx: &mut Context,
     let id = summary.package_id();
     let key = (id.name().to_string(), id.source_id().clone());
     let prev = cx.activations.entry(key).get().unwrap_or_else(|e| {
-        e.insert(Vec::new())
+        e.insert(Rc::new(Vec::new()))
     });
     if !prev.iter().any(|c| c == summary) {
-        cx.resolve.graph.add(id.clone(), &[]);
-        prev.push(summary.clone());
+        let mut summaries = (**prev).clone();
+        summaries.push(summary.clone());
+        *prev = Rc::new(summaries);
         return false
     }
     debug!("checking if {} is already activated", summary.package_id());
@@ -233,103 +594,62 @@ fn flag_activated(cx: &mut Context,
         Method::Required{features, ..} => features,
         Method::Everything => return false,
     };
-    match cx.resolve.features(id) {
+    match cx.resolve_features.get(id) {
         Some(prev) => features.iter().all(|f| prev.contains(f)),
         None => features.len() == 0,
     }
 }
 
-fn activate_deps<'a>(cx: Box<Context>,
-                     registry: &mut Registry,
-                     parent: &Summary,
-                     platform: Option<&'a str>,
-                     deps: &'a [(&Dependency, Vec<Rc<Summary>>, Vec<String>)],
-                     cur: usize) -> CargoResult<CargoResult<Box<Context>>> {
-    if cur == deps.len() { return Ok(Ok(cx)) }
-    let (dep, ref candidates, ref features) = deps[cur];
-
-    let method = Method::Required{
-        dev_deps: false,
-        features: &features,
-        uses_default_features: dep.uses_default_features(),
-        target_platform: platform};
-
-    let key = (dep.name().to_string(), dep.source_id().clone());
-    let prev_active = cx.activations.get(&key)
-                                    .map(|v| v.as_slice()).unwrap_or(&[]);
-    trace!("{}[{}]>{} {} candidates", parent.name(), cur, dep.name(),
-         candidates.len());
-    trace!("{}[{}]>{} {} prev activations", parent.name(), cur,
-         dep.name(), prev_active.len());
-
-    // Filter the set of candidates based on the previously activated
-    // versions for this dependency. We can actually use a version if it
-    // precisely matches an activated version or if it is otherwise
-    // incompatible with all other activated versions. Note that we define
-    // "compatible" here in terms of the semver sense where if the left-most
-    // nonzero digit is the same they're considered compatible.
-    let my_candidates = candidates.iter().filter(|&b| {
-        prev_active.iter().any(|a| a == b) ||
-            prev_active.iter().all(|a| {
-                !compatible(a.version(), b.version())
-            })
-    });
+// Dependency graphs are required to be a DAG. Cycles through dev-dependencies
+// are fine though, as those aren't needed to build the package itself.
+fn check_cycles(decisions: &[BacktrackFrame]) -> CargoResult<()> {
+    let mut edges = HashMap::new();
+    for frame in decisions.iter().filter(|f| f.dep.is_transitive()) {
+        match edges.entry(frame.parent.package_id()) {
+            Occupied(e) => e.into_mut(),
+            Vacant(e) => e.insert(Vec::new()),
+        }.push(frame.current.package_id());
+    }
 
-    // Alright, for each candidate that's gotten this far, it meets the
-    // following requirements:
-    //
-    // 1. The version matches the dependency requirement listed for this
-    //    package
-    // 2. There are no activated versions for this package which are
-    //    semver-compatible, or there's an activated version which is
-    //    precisely equal to `candidate`.
-    //
-    // This means that we're going to attempt to activate each candidate in
-    // turn. We could possibly fail to activate each candidate, so we try
-    // each one in turn.
-    let mut last_err = None;
-    for candidate in my_candidates {
-        trace!("{}[{}]>{} trying {}", parent.name(), cur, dep.name(),
-             candidate.version());
-        let mut my_cx = cx.clone();
-        my_cx.resolve.graph.link(parent.package_id().clone(),
-                                 candidate.package_id().clone());
-
-        // If we hit an intransitive dependency then clear out the visitation
-        // list as we can't induce a cycle through transitive dependencies.
-        if !dep.is_transitive() {
-            my_cx.visited.borrow_mut().clear();
-        }
-        let my_cx = match try!(activate(my_cx, registry, candidate, method)) {
-            Ok(cx) => cx,
-            Err(e) => { last_err = Some(e); continue }
-        };
-        match try!(activate_deps(my_cx, registry, parent, platform, deps,
-                                 cur + 1)) {
-            Ok(cx) => return Ok(Ok(cx)),
-            Err(e) => { last_err = Some(e); }
+    // An iterative depth first search, as the graph may be arbitrarily deep.
+    let mut done = HashSet::new();
+    for &start in edges.keys() {
+        if done.contains(start) { continue }
+        let mut on_path = HashSet::new();
+        let mut stack = vec![(start, 0)];
+        on_path.insert(start);
+        while stack.len() > 0 {
+            let (id, i) = stack[stack.len() - 1];
+            let children = edges.get(id).map(|v| v.as_slice()).unwrap_or(&[]);
+            if i < children.len() {
+                stack.last_mut().unwrap().1 += 1;
+                let child = children[i];
+                if on_path.contains(child) {
+                    return Err(human(format!("cyclic package dependency: \
+                                              package `{}` depends on itself",
+                                             child)))
+                }
+                if !done.contains(child) {
+                    on_path.insert(child);
+                    stack.push((child, 0));
+                }
+            } else {
+                stack.pop();
+                on_path.remove(id);
+                done.insert(id);
+            }
         }
     }
-    trace!("{}[{}]>{} -- {:?}", parent.name(), cur, dep.name(),
-         last_err);
-
-    // Oh well, we couldn't activate any of the candidates, so we just can't
-    // activate this dependency at all
-    Ok(activation_error(&cx, registry, last_err, parent, dep, prev_active,
-                        &candidates))
+    Ok(())
 }
 
 fn activation_error(cx: &Context,
-                    registry: &mut Registry,
-                    err: Option<Box<CargoError>>,
+                    registry: &mut RegistryQueryer,
+                    decisions: &[BacktrackFrame],
                     parent: &Summary,
                     dep: &Dependency,
-                    prev_active: &[Rc<Summary>],
-                    candidates: &[Rc<Summary>]) -> CargoResult<Box<Context>> {
-    match err {
-        Some(e) => return Err(e),
-        None => {}
-    }
+                    candidates: &[Rc<Summary>]) -> CargoResult<String> {
+    let parents = first_parents(decisions);
     if candidates.len() > 0 {
         let mut msg = format!("failed to select a version for `{}` \
                                (required by `{}`):\n\
@@ -337,32 +657,39 @@ fn activation_error(cx: &Context,
                                previously selected versions of `{}`",
                               dep.name(), parent.name(),
                               dep.name());
-        'outer: for v in prev_active.iter() {
-            for node in cx.resolve.graph.iter() {
-                let edges = match cx.resolve.graph.edges(node) {
-                    Some(edges) => edges,
-                    None => continue,
-                };
-                for edge in edges {
-                    if edge != v.package_id() { continue }
-
-                    msg.push_str(&format!("\n  version {} in use by {}",
-                                          v.version(), edge));
-                    continue 'outer;
-                }
-            }
-            msg.push_str(&format!("\n  version {} in use by ??",
+        let mut loosen = Vec::new();
+        for v in cx.prev_active(dep).iter() {
+            let chain = requirement_chain(&parents, v.package_id());
+            msg.push_str(&format!("\n\n  version {} was selected through:\n",
                                   v.version()));
+            msg.push_str(&format_chain(&chain, None));
+            match parents.get(v.package_id()) {
+                Some(frame) => loosen.push((frame.parent.package_id().clone(),
+                                            (*frame.dep).clone())),
+                None => {}
+            }
         }
 
-        msg.push_str(&format!("\n  possible versions to select: {}",
+        msg.push_str(&format!("\n\n  but `{}` is also required through:\n",
+                              dep.name()));
+        let chain = requirement_chain(&parents, parent.package_id());
+        msg.push_str(&format_chain(&chain, Some(dep)));
+        loosen.push((parent.package_id().clone(), dep.clone()));
+
+        msg.push_str(&format!("\n\n  possible versions to select: {}",
                               candidates.iter()
                                         .map(|v| v.version())
                                         .map(|v| v.to_string())
                                         .collect::<Vec<_>>()
                                         .connect(", ")));
 
-        return Err(human(msg))
+        msg.push_str("\n\nconsider loosening one of these requirements:");
+        for &(ref by, ref req) in loosen.iter() {
+            msg.push_str(&format!("\n  `{} {}` of `{} v{}`", req.name(),
+                                  req.version_req(), by.name(), by.version()));
+        }
+
+        return Ok(msg)
     }
     // Once we're all the way down here, we're definitely lost in the
     // weeds! We didn't actually use any candidates above, so we need to
@@ -381,7 +708,7 @@ fn activation_error(cx: &Context,
     let mut msg = msg;
     let all_req = semver::VersionReq::parse("*").unwrap();
     let new_dep = dep.clone().set_version_req(all_req);
-    let mut candidates = try!(registry.query(&new_dep));
+    let mut candidates = try!(registry.registry.query(&new_dep));
     candidates.sort_by(|a, b| {
         b.version().cmp(a.version())
     });
@@ -406,7 +733,74 @@ fn activation_error(cx: &Context,
                       a path dependency's locked version");
 
     }
-    Err(human(msg))
+    Ok(msg)
+}
+
+// Maps each package to the decision which first activated it.
+fn first_parents(decisions: &[BacktrackFrame])
+                 -> HashMap<&PackageId, &BacktrackFrame> {
+    let mut parents = HashMap::new();
+    for frame in decisions.iter() {
+        let id = frame.current.package_id();
+        if !parents.contains_key(id) {
+            parents.insert(id, frame);
+        }
+    }
+    parents
+}
+
+// Returns the path of requirements through which `id` was activated, starting
+// at the root package. Every element is a package along with the requirement
+// that selected it, which is `None` only for the root.
+fn requirement_chain(parents: &HashMap<&PackageId, &BacktrackFrame>,
+                     id: &PackageId) -> Vec<(PackageId, Option<Dependency>)> {
+    let mut chain = vec![(id.clone(), None)];
+    loop {
+        let next = match parents.get(&chain.last().unwrap().0) {
+            Some(frame) => (frame.parent.package_id().clone(),
+                            (*frame.dep).clone()),
+            None => break,
+        };
+        // Dev-dependencies may lead back to the root package.
+        if chain.iter().any(|&(ref id, _)| *id == next.0) { break }
+        chain.last_mut().unwrap().1 = Some(next.1);
+        chain.push((next.0, None));
+    }
+    chain.reverse();
+    chain
+}
+
+// Renders a requirement chain as a tree, one level per line. If `last` is
+// given it is appended as an unsatisfied requirement of the final package.
+fn format_chain(chain: &[(PackageId, Option<Dependency>)],
+                last: Option<&Dependency>) -> String {
+    let mut lines = Vec::new();
+    for (i, &(ref id, ref req)) in chain.iter().enumerate() {
+        let line = match *req {
+            Some(ref req) => format!("{} {} -> {} v{}", req.name(),
+                                     req.version_req(), id.name(),
+                                     id.version()),
+            None => format!("{} v{}", id.name(), id.version()),
+        };
+        lines.push(tree_line(i, &line));
+    }
+    match last {
+        Some(dep) => {
+            let line = format!("{} {}", dep.name(), dep.version_req());
+            lines.push(tree_line(chain.len(), &line));
+        }
+        None => {}
+    }
+    lines.connect("\n")
+}
+
+fn tree_line(depth: usize, line: &str) -> String {
+    if depth == 0 {
+        format!("    {}", line)
+    } else {
+        format!("    {}└── {}", repeat("    ").take(depth - 1).collect::<String>(),
+                line)
+    }
 }
 
 // Returns if `a` and `b` are compatible in the semver sense. This is a
@@ -485,10 +879,13 @@ fn resolve_features<'a>(cx: &mut Context, parent: &'a Summary,
     // Record what list of features is active for this package.
     if used_features.len() > 0 {
         let pkgid = parent.package_id();
-        match cx.resolve.features.entry(pkgid.clone()) {
+        let features = match cx.resolve_features.entry(pkgid.clone()) {
             Occupied(entry) => entry.into_mut(),
-            Vacant(entry) => entry.insert(HashSet::new()),
-        }.extend(used_features.into_iter());
+            Vacant(entry) => entry.insert(Rc::new(HashSet::new())),
+        };
+        let mut set = (**features).clone();
+        set.extend(used_features.into_iter());
+        *features = Rc::new(set);
     }
 
     Ok(ret)
@@ -10,7 +10,7 @@ use rustc_serialize::{Decodable, Decoder, Encodable, Encoder};
 use url::Url;
 
 use core::{Summary, Package, PackageId, Registry, Dependency};
-use sources::{PathSource, GitSource, RegistrySource};
+use sources::{PathSource, GitSource, RegistrySource, DirectorySource};
 use sources::git;
 use util::{human, Config, CargoResult, CargoError, ToUrl};
 
@@ -52,6 +52,10 @@ enum Kind {
     Path,
     /// represents the central registry
     Registry,
+    /// represents a registry whose index and tarballs are in a local directory
+    LocalRegistry,
+    /// represents a local directory of unpacked packages with checksum files
+    Directory,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -129,6 +133,15 @@ impl SourceId {
                 SourceId::new(Kind::Registry, url)
                          .with_precise(Some("locked".to_string()))
             }
+            "local-registry" => {
+                let url = url.to_url().unwrap();
+                SourceId::new(Kind::LocalRegistry, url)
+                         .with_precise(Some("locked".to_string()))
+            }
+            "directory" => {
+                let url = url.to_url().unwrap();
+                SourceId::new(Kind::Directory, url)
+            }
             "path" => SourceId::for_path(Path::new(&url[5..])).unwrap(),
             _ => panic!("Unsupported serialized SourceId")
         }
@@ -156,6 +169,12 @@ impl SourceId {
             SourceIdInner { kind: Kind::Registry, ref url, .. } => {
                 format!("registry+{}", url)
             }
+            SourceIdInner { kind: Kind::LocalRegistry, ref url, .. } => {
+                format!("local-registry+{}", url)
+            }
+            SourceIdInner { kind: Kind::Directory, ref url, .. } => {
+                format!("directory+{}", url)
+            }
         }
     }
 
@@ -173,17 +192,45 @@ impl SourceId {
         SourceId::new(Kind::Registry, url.clone())
     }
 
+    // Pass absolute path
+    pub fn for_local_registry(path: &Path) -> CargoResult<SourceId> {
+        let url = try!(path.to_url().map_err(human));
+        Ok(SourceId::new(Kind::LocalRegistry, url))
+    }
+
+    // Pass absolute path
+    pub fn for_directory(path: &Path) -> CargoResult<SourceId> {
+        let url = try!(path.to_url().map_err(human));
+        Ok(SourceId::new(Kind::Directory, url))
+    }
+
     /// Returns the `SourceId` corresponding to the main repository.
     ///
     /// This is the main cargo registry by default, but it can be overridden in
-    /// a `.cargo/config`.
+    /// a `.cargo/config`, either with the URL of another registry or with a
+    /// local registry or directory standing in for it.
     pub fn for_central(config: &Config) -> CargoResult<SourceId> {
+        if let Some(path) = try!(config.get_path("registry.local-registry")) {
+            return SourceId::for_local_registry(&path)
+        }
+        if let Some(path) = try!(config.get_path("registry.directory")) {
+            return SourceId::for_directory(&path)
+        }
         Ok(SourceId::for_registry(&try!(RegistrySource::url(config))))
     }
 
     pub fn url(&self) -> &Url { &self.inner.url }
     pub fn is_path(&self) -> bool { self.inner.kind == Kind::Path }
-    pub fn is_registry(&self) -> bool { self.inner.kind == Kind::Registry }
+    pub fn is_local_registry(&self) -> bool {
+        self.inner.kind == Kind::LocalRegistry
+    }
+    pub fn is_directory(&self) -> bool { self.inner.kind == Kind::Directory }
+
+    /// Whether this is a registry, remote or local. Both provide many versions
+    /// of every package and are queried the same way.
+    pub fn is_registry(&self) -> bool {
+        self.inner.kind == Kind::Registry || self.is_local_registry()
+    }
 
     pub fn is_git(&self) -> bool {
         match self.inner.kind {
@@ -204,9 +251,16 @@ impl SourceId {
                 };
                 Box::new(PathSource::new(&path, self, config)) as Box<Source>
             },
-            Kind::Registry => {
+            Kind::Registry | Kind::LocalRegistry => {
                 Box::new(RegistrySource::new(self, config)) as Box<Source>
             }
+            Kind::Directory => {
+                let path = match self.inner.url.to_file_path::<PathBuf>() {
+                    Ok(p) => p,
+                    Err(()) => panic!("directory sources cannot be remote"),
+                };
+                Box::new(DirectorySource::new(&path, self, config)) as Box<Source>
+            }
         }
     }
 
@@ -295,6 +349,12 @@ impl fmt::Display for SourceId {
             SourceIdInner { kind: Kind::Registry, ref url, .. } => {
                 write!(f, "registry {}", url)
             }
+            SourceIdInner { kind: Kind::LocalRegistry, ref url, .. } => {
+                write!(f, "local registry {}", url)
+            }
+            SourceIdInner { kind: Kind::Directory, ref url, .. } => {
+                write!(f, "directory {}", url)
+            }
         }
     }
 }
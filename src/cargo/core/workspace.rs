@@ -0,0 +1,109 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use core::{Package, PackageId, Source, SourceId};
+use ops;
+use sources::PathSource;
+use util::{self, CargoResult, Config, ChainError, human};
+
+/// A set of packages sharing one `Cargo.lock` and one target directory.
+///
+/// The root of a workspace is a package whose manifest contains a
+/// `[workspace]` table listing the paths of the other members. Packages which
+/// aren't a member of any workspace make up a workspace of their own.
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    // The package the command was invoked for.
+    current: PackageId,
+    // The root comes first, followed by the other members in the order they
+    // are listed in.
+    members: Vec<Package>,
+}
+
+impl Workspace {
+    /// Loads the workspace the package at `manifest_path` is a member of.
+    ///
+    /// All members are loaded through the source of the root package, and
+    /// their target directory is the one of the root.
+    pub fn new(manifest_path: &Path, config: &Config) -> CargoResult<Workspace> {
+        let dir = manifest_path.parent().unwrap();
+        let root_dir = try!(find_root(dir, config)).unwrap_or(dir.to_path_buf());
+
+        let mut source = try!(PathSource::for_path(&root_dir, config));
+        try!(source.update());
+        let root = try!(source.root_package());
+        let packages = try!(source.read_packages());
+
+        let mut members = vec![root.clone()];
+        for path in root.manifest().workspace_members().unwrap_or(&[]).iter() {
+            let path = util::normalize_path(&root_dir.join(path));
+            let pkg = try!(packages.iter().find(|p| p.root() == &*path)
+                                   .chain_error(|| {
+                human(format!("workspace member `{}` of `{}` could not be found",
+                              path.display(), root.package_id()))
+            }));
+            if members.contains(pkg) { continue }
+            let mut manifest = pkg.manifest().clone();
+            manifest.set_target_dir(root.absolute_target_dir());
+            members.push(Package::new(manifest, pkg.manifest_path(),
+                                      pkg.package_id().source_id()));
+        }
+
+        let current = match members.iter().find(|p| p.root() == dir) {
+            Some(pkg) => pkg.package_id().clone(),
+            None => {
+                return Err(human(format!("package at `{}` is not a member of \
+                                          the workspace at `{}`", dir.display(),
+                                         root_dir.display())))
+            }
+        };
+        Ok(Workspace { current: current, members: members })
+    }
+
+    /// Creates a workspace made up of `package` only.
+    pub fn single(package: Package) -> Workspace {
+        Workspace {
+            current: package.package_id().clone(),
+            members: vec![package],
+        }
+    }
+
+    /// The package holding the `Cargo.lock` and target directory.
+    pub fn root(&self) -> &Package { &self.members[0] }
+
+    /// The package the command was invoked for.
+    pub fn current(&self) -> &Package {
+        self.members.iter().find(|p| *p.package_id() == self.current).unwrap()
+    }
+
+    /// All packages of the workspace, including the root.
+    pub fn members(&self) -> &[Package] { &self.members }
+
+    pub fn is_member(&self, id: &PackageId) -> bool {
+        self.members.iter().any(|p| p.package_id() == id)
+    }
+}
+
+// Looks for the closest directory at or above `dir` whose manifest lists
+// `dir` as a member of its workspace.
+fn find_root(dir: &Path, config: &Config) -> CargoResult<Option<PathBuf>> {
+    let mut current = Some(dir);
+    while let Some(candidate) = current {
+        current = candidate.parent();
+        let manifest = candidate.join("Cargo.toml");
+        if fs::metadata(&manifest).is_err() { continue }
+
+        let id = try!(SourceId::for_path(candidate));
+        let (pkg, _) = try!(ops::read_package(&manifest, &id, config));
+        let members = match pkg.manifest().workspace_members() {
+            Some(members) => members,
+            None => continue,
+        };
+        if candidate == dir || members.iter().any(|m| {
+            util::normalize_path(&candidate.join(m)) == dir
+        }) {
+            return Ok(Some(candidate.to_path_buf()))
+        }
+    }
+    Ok(None)
+}
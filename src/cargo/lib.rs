@@ -2,8 +2,10 @@
 #![feature(hash, os, std_misc, unicode, core)]
 #![feature(io, path, str_words, old_io, exit_status, fs_time)]
 #![cfg_attr(test, deny(warnings))]
+#![cfg_attr(test, feature(test))]
 
 #[cfg(test)] extern crate hamcrest;
+#[cfg(test)] extern crate test;
 #[macro_use] extern crate log;
 extern crate "rustc-serialize" as rustc_serialize;
 extern crate curl;
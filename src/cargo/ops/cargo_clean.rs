@@ -3,32 +3,28 @@ use std::fs;
 use std::io::prelude::*;
 use std::path::Path;
 
-use core::PackageSet;
+use core::{PackageSet, Workspace};
 use core::source::{Source, SourceMap};
-use sources::PathSource;
 use util::{CargoResult, human, ChainError, Config};
 use ops::{self, Layout, Context};
 
 pub struct CleanOptions<'a, 'b: 'a> {
-    pub spec: Option<&'a str>,
+    /// Specs of the packages to clean, everything is removed if empty.
+    pub spec: &'a [String],
     pub target: Option<&'a str>,
     pub config: &'a Config<'b>,
 }
 
 /// Cleans the project from build artifacts.
 pub fn clean(manifest_path: &Path, opts: &CleanOptions) -> CargoResult<()> {
-    let mut src = try!(PathSource::for_path(manifest_path.parent().unwrap(),
-                                            opts.config));
-    try!(src.update());
-    let root = try!(src.root_package());
-    let manifest = root.manifest();
+    let ws = try!(Workspace::new(manifest_path, opts.config));
+    let root = ws.root();
 
     // If we have a spec, then we need to delete some package,s otherwise, just
     // remove the whole target directory and be done with it!
-    let spec = match opts.spec {
-        Some(spec) => spec,
-        None => return rm_rf(manifest.target_dir()),
-    };
+    if opts.spec.len() == 0 {
+        return rm_rf(&root.absolute_target_dir())
+    }
 
     // Load the lockfile (if one's available), and resolve spec to a pkgid
     let lockfile = root.root().join("Cargo.lock");
@@ -37,30 +33,35 @@ pub fn clean(manifest_path: &Path, opts: &CleanOptions) -> CargoResult<()> {
         Some(resolve) => resolve,
         None => return Err(human("A Cargo.lock must exist before cleaning"))
     };
-    let pkgid = try!(resolve.query(spec));
 
-    // Translate the PackageId to a Package
-    let pkg = {
-        let mut source = pkgid.source_id().load(opts.config);
-        try!(source.update());
-        (try!(source.get(&[pkgid.clone()]))).into_iter().next().unwrap()
-    };
+    for spec in opts.spec.iter() {
+        let pkgid = try!(resolve.query(spec));
+
+        // Translate the PackageId to a Package
+        let pkg = {
+            let mut source = pkgid.source_id().load(opts.config);
+            try!(source.update());
+            (try!(source.get(&[pkgid.clone()]))).into_iter().next().unwrap()
+        };
 
-    // Create a compilation context to have access to information like target
-    // filenames and such
-    let srcs = SourceMap::new();
-    let pkgs = PackageSet::new(&[]);
-    let cx = try!(Context::new("compile", &resolve, &srcs, &pkgs, opts.config,
-                               Layout::at(root.absolute_target_dir()),
-                               None, &pkg, Default::default()));
+        // Create a compilation context to have access to information like
+        // target filenames and such
+        let srcs = SourceMap::new();
+        let pkgs = PackageSet::new(&[]);
+        let cx = try!(Context::new("compile", &resolve, &srcs, &pkgs,
+                                   opts.config,
+                                   Layout::at(root.absolute_target_dir()),
+                                   None, &pkg, Default::default()));
 
-    // And finally, clean everything out!
-    for target in pkg.targets().iter() {
-        let layout = Layout::new(&root, opts.target, target.profile().dest());
-        try!(rm_rf(&layout.fingerprint(&pkg)));
-        for filename in try!(cx.target_filenames(target)).iter() {
-            try!(rm_rf(&layout.dest().join(&filename)));
-            try!(rm_rf(&layout.deps().join(&filename)));
+        // And finally, clean everything out!
+        for target in pkg.targets().iter() {
+            let layout = Layout::new(root, opts.target,
+                                     target.profile().dest());
+            try!(rm_rf(&layout.fingerprint(&pkg)));
+            for filename in try!(cx.target_filenames(target)).iter() {
+                try!(rm_rf(&layout.dest().join(&filename)));
+                try!(rm_rf(&layout.deps().join(&filename)));
+            }
         }
     }
 
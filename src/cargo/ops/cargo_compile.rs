@@ -24,17 +24,18 @@
 
 use std::collections::HashMap;
 use std::default::Default;
+use std::env;
 use std::num::ToPrimitive;
 use std::os;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::slice;
 use std::sync::Arc;
 
 use core::registry::PackageRegistry;
-use core::{Source, SourceId, PackageSet, Package, Target, PackageId};
-use core::resolver::Method;
+use core::{SourceId, PackageSet, Package, Target, PackageId, Workspace};
+use core::resolver::{Method, ResolveOptions};
 use ops::{self, BuildOutput, ExecEngine};
-use sources::{PathSource};
-use util::config::Config;
+use util::config::{Config, ConfigValue};
 use util::{CargoResult, internal, human, ChainError, profile};
 
 /// Contains informations about how a package should be compiled.
@@ -49,42 +50,52 @@ pub struct CompileOptions<'a, 'b: 'a> {
     pub dev_deps: bool,
     pub features: &'a [String],
     pub no_default_features: bool,
-    pub spec: Option<&'a str>,
+    /// Specs of the packages to build instead of the current one.
+    pub spec: &'a [String],
+    /// True if every member of the workspace must be built.
+    pub all_members: bool,
     pub lib_only: bool,
+    /// Name of the only binary to build, if any.
+    pub bin_only: Option<&'a str>,
+    /// Extra arguments to pass to rustc for the one target being built.
+    pub target_rustc_args: Option<&'a [String]>,
+    /// Name of a custom profile of the root manifest to build with.
+    pub profile: Option<&'a str>,
     pub exec_engine: Option<Arc<Box<ExecEngine>>>,
 }
 
 pub fn compile(manifest_path: &Path,
                options: &CompileOptions)
-               -> CargoResult<ops::Compilation> {
+               -> CargoResult<Vec<ops::Compilation>> {
     debug!("compile; manifest-path={}", manifest_path.display());
 
-    let mut source = try!(PathSource::for_path(manifest_path.parent().unwrap(),
-                                               options.config));
-    try!(source.update());
+    let ws = try!(Workspace::new(manifest_path, options.config));
+    debug!("loaded package; package={}", ws.current());
 
-    // TODO: Move this into PathSource
-    let package = try!(source.root_package());
-    debug!("loaded package; package={}", package);
-
-    for key in package.manifest().warnings().iter() {
+    for key in ws.current().manifest().warnings().iter() {
         try!(options.config.shell().warn(key))
     }
-    compile_pkg(&package, options)
+    compile_ws(&ws, options)
 }
 
-pub fn compile_pkg(package: &Package, options: &CompileOptions)
-                   -> CargoResult<ops::Compilation> {
-    let CompileOptions { env, config, jobs, target, spec,
+/// Compiles the packages of `ws` selected by `options`, returning one
+/// compilation for each of them.
+pub fn compile_ws(ws: &Workspace, options: &CompileOptions)
+                  -> CargoResult<Vec<ops::Compilation>> {
+    let CompileOptions { config, jobs, target, spec, all_members,
                          dev_deps, features, no_default_features,
-                         lib_only, ref exec_engine } = *options;
+                         lib_only, bin_only, target_rustc_args,
+                         ref exec_engine, .. } = *options;
+    let env = try!(target_env(ws, options));
+    let env = &env[..];
 
     let target = target.map(|s| s.to_string());
     let features = features.iter().flat_map(|s| {
         s.split(' ')
     }).map(|s| s.to_string()).collect::<Vec<String>>();
 
-    if spec.is_some() && (no_default_features || features.len() > 0) {
+    if (all_members || spec.len() > 0) &&
+       (no_default_features || features.len() > 0) {
         return Err(human("features cannot be modified when the main package \
                           is not being built"))
     }
@@ -92,15 +103,34 @@ pub fn compile_pkg(package: &Package, options: &CompileOptions)
         return Err(human("jobs must be at least 1"))
     }
 
-    let override_ids = try!(source_ids_from_config(config, package.root()));
+    let override_ids = try!(source_ids_from_config(config, ws));
 
-    let (packages, resolve_with_overrides, sources) = {
+    let (builds, sources) = {
         let rustc_host = config.rustc_host().to_string();
         let mut registry = PackageRegistry::new(config);
 
-        // First, resolve the package's *listed* dependencies, as well as
-        // downloading and updating all remotes and such.
-        let resolve = try!(ops::resolve_pkg(&mut registry, package));
+        // First, resolve the *listed* dependencies of the whole workspace, as
+        // well as downloading and updating all remotes and such.
+        let resolve = try!(ops::resolve_ws(&mut registry, ws, config));
+
+        // Members of the workspace are built on their own, anything else is
+        // built as a dependency of the current package.
+        let mut to_build = Vec::new();
+        if all_members {
+            to_build.extend(ws.members().iter().map(|p| (p.clone(), None)));
+        } else if spec.len() == 0 {
+            to_build.push((ws.current().clone(), None));
+        }
+        for spec in spec.iter().filter(|_| !all_members) {
+            let pkgid = try!(resolve.query(spec));
+            let build = match ws.members().iter().find(|p| p.package_id() == pkgid) {
+                Some(member) => (member.clone(), None),
+                None => (ws.current().clone(), Some(spec.clone())),
+            };
+            if !to_build.contains(&build) {
+                to_build.push(build);
+            }
+        }
 
         // Second, resolve with precisely what we're doing. Filter out
         // transitive dependencies if necessary, specify features, handle
@@ -117,54 +147,117 @@ pub fn compile_pkg(package: &Package, options: &CompileOptions)
             uses_default_features: !no_default_features,
             target_platform: platform};
 
-        let resolved_with_overrides =
-                try!(ops::resolve_with_previous(&mut registry, package, method,
-                                                Some(&resolve), None));
-
-        let req: Vec<PackageId> = resolved_with_overrides.iter().map(|r| {
-            r.clone()
-        }).collect();
-        let packages = try!(registry.get(&req).chain_error(|| {
-            human("Unable to get packages from source")
-        }));
+        let mut builds = Vec::new();
+        for (root, spec) in to_build.into_iter() {
+            let resolved_with_overrides =
+                    try!(ops::resolve_with_previous(&mut registry, ws,
+                                                    slice::ref_slice(&root),
+                                                    method,
+                                                    ResolveOptions::default(),
+                                                    Some(&resolve), None));
+
+            let req: Vec<PackageId> = resolved_with_overrides.iter().map(|r| {
+                r.clone()
+            }).collect();
+            let packages = try!(registry.get(&req).chain_error(|| {
+                human("Unable to get packages from source")
+            }));
+
+            // Members of the workspace are built into the target directory of
+            // its root.
+            let packages = packages.into_iter().map(|pkg| {
+                ws.members().iter().find(|m| **m == pkg).cloned().unwrap_or(pkg)
+            }).collect::<Vec<Package>>();
+
+            let pkgid = match spec {
+                Some(spec) => try!(resolved_with_overrides.query(&spec)).clone(),
+                None => root.package_id().clone(),
+            };
+            builds.push((pkgid, packages, resolved_with_overrides));
+        }
 
-        (packages, resolved_with_overrides, registry.move_sources())
+        (builds, registry.move_sources())
     };
 
-    let to_build = match spec {
-        Some(spec) => {
-            let pkgid = try!(resolve_with_overrides.query(spec));
-            packages.iter().find(|p| p.package_id() == pkgid).unwrap()
+    let mut lib_overrides = try!(scrape_build_config(config, jobs, target));
+    lib_overrides.profile = options.profile.map(|s| s.to_string());
+    lib_overrides.profiles = ws.root().manifest().profiles().clone();
+    let mut ret = Vec::new();
+
+    for &(ref pkgid, ref packages, ref resolve) in builds.iter() {
+        let to_build = packages.iter().find(|p| p.package_id() == pkgid).unwrap();
+
+        let targets = to_build.targets().iter().filter(|target| {
+            target.profile().is_custom_build() || match env {
+                // doc-all == document everything, so look for doc targets
+                "doc" | "doc-all" => target.profile().env() == "doc",
+                env => target.profile().env() == env,
+            }
+        }).filter(|target| !lib_only || target.is_lib()).filter(|target| {
+            // The binaries of the package may link to its library, which is
+            // kept even when a single binary is selected.
+            target.profile().is_custom_build() || target.is_lib() ||
+                bin_only.map_or(true, |name| {
+                    target.is_bin() && target.name() == name
+                })
+        }).collect::<Vec<&Target>>();
+
+        if lib_only && targets.len() == 0 {
+            return Err(human("There is no lib to build, remove `--lib` flag".to_string()));
         }
-        None => package,
-    };
 
-    let targets = to_build.targets().iter().filter(|target| {
-        target.profile().is_custom_build() || match env {
-            // doc-all == document everything, so look for doc targets
-            "doc" | "doc-all" => target.profile().env() == "doc",
-            env => target.profile().env() == env,
+        // The targets the user asked for, as opposed to the build scripts and
+        // libraries they need.
+        let selected = targets.iter().filter(|t| {
+            !t.profile().is_custom_build() && bin_only.map_or(true, |name| {
+                t.is_bin() && t.name() == name
+            })
+        }).collect::<Vec<_>>();
+        if let Some(name) = bin_only {
+            if selected.len() == 0 {
+                return Err(human(format!("no bin target named `{}`", name)));
+            }
+        }
+        let mut build_config = lib_overrides.clone();
+        if let Some(args) = target_rustc_args {
+            if selected.len() != 1 {
+                return Err(human("extra arguments to `rustc` can only be \
+                                  passed to one target, consider filtering \
+                                  the package by passing e.g. `--lib` or \
+                                  `--bin NAME` to specify a single target"))
+            }
+            build_config.target_rustc_args = Some(((**selected[0]).clone(),
+                                                   args.to_vec()));
         }
-    }).filter(|target| !lib_only || target.is_lib()).collect::<Vec<&Target>>();
 
-    if lib_only && targets.len() == 0 {
-        return Err(human("There is no lib to build, remove `--lib` flag".to_string()));
+        let _p = profile::start("compiling");
+        ret.push(try!(ops::compile_targets(&env, &targets, to_build,
+                                           &PackageSet::new(packages),
+                                           resolve, &sources, config,
+                                           build_config,
+                                           exec_engine.clone())));
     }
 
-    let ret = {
-        let _p = profile::start("compiling");
-        let lib_overrides = try!(scrape_build_config(config, jobs, target));
+    Ok(ret)
+}
 
-        try!(ops::compile_targets(&env, &targets, to_build,
-                                  &PackageSet::new(&packages),
-                                  &resolve_with_overrides, &sources,
-                                  config, lib_overrides, exec_engine.clone()))
+/// Returns the environment of the targets built for `options`, which is the one
+/// of the built-in profile inherited by the custom profile to build with.
+pub fn target_env(ws: &Workspace, options: &CompileOptions)
+                  -> CargoResult<String> {
+    let name = match options.profile {
+        Some(name) => name,
+        None => return Ok(options.env.to_string()),
     };
-
-    return Ok(ret);
+    let profiles = ws.root().manifest().profiles();
+    let custom = try!(profiles.custom(name).chain_error(|| {
+        human(format!("profile `{}` is not defined in the manifest of `{}`",
+                      name, ws.root()))
+    }));
+    Ok(custom.env.clone())
 }
 
-fn source_ids_from_config(config: &Config, cur_path: &Path)
+fn source_ids_from_config(config: &Config, ws: &Workspace)
                           -> CargoResult<Vec<SourceId>> {
 
     let configs = try!(config.values());
@@ -183,9 +276,9 @@ fn source_ids_from_config(config: &Config, cur_path: &Path)
         // to get the directory containing the `.cargo` folder.
         p.parent().unwrap().parent().unwrap().join(s)
     }).filter(|p| {
-        // Make sure we don't override the local package, even if it's in the
-        // list of override paths.
-        cur_path != &**p
+        // Make sure we don't override the local packages, even if they're in
+        // the list of override paths.
+        !ws.members().iter().any(|m| m.root() == &**p)
     }).map(|p| SourceId::for_path(&p)).collect()
 }
 
@@ -214,14 +307,48 @@ fn scrape_build_config(config: &Config,
         requested_target: target.clone(),
         ..Default::default()
     };
-    base.host = try!(scrape_target_config(config, config.rustc_host()));
+    // When cross compiling, plugins and build scripts are built with the
+    // flags configured for the host, `RUSTFLAGS` being meant for the target.
+    let host = config.rustc_host();
+    base.host = try!(scrape_target_config(config, host));
+    base.host.rustflags = try!(scrape_rustflags(config, host, target.is_none()));
     base.target = match target.as_ref() {
-        Some(triple) => try!(scrape_target_config(config, &triple)),
+        Some(triple) => {
+            let mut ret = try!(scrape_target_config(config, &triple));
+            ret.rustflags = try!(scrape_rustflags(config, &triple, true));
+            ret
+        }
         None => base.host.clone(),
     };
     Ok(base)
 }
 
+/// Returns the extra flags to pass to rustc for `triple`, taken from the
+/// `RUSTFLAGS` environment variable if `from_env` is set,
+/// `target.<triple>.rustflags` or `build.rustflags`, whichever is found first.
+fn scrape_rustflags(config: &Config, triple: &str, from_env: bool)
+                    -> CargoResult<Vec<String>> {
+    if from_env {
+        if let Some(flags) = env::var("RUSTFLAGS").ok() {
+            return Ok(split_flags(&flags))
+        }
+    }
+    let keys = [format!("target.{}.rustflags", triple),
+                "build.rustflags".to_string()];
+    for key in keys.iter() {
+        match try!(config.get(key)) {
+            Some(ConfigValue::String(s, _)) => return Ok(split_flags(&s)),
+            Some(value) => return string_list(&value, key),
+            None => {}
+        }
+    }
+    Ok(Vec::new())
+}
+
+fn split_flags(flags: &str) -> Vec<String> {
+    flags.words().map(|s| s.to_string()).collect()
+}
+
 fn scrape_target_config(config: &Config, triple: &str)
                         -> CargoResult<ops::TargetConfig> {
     let key = format!("target.{}", triple);
@@ -231,6 +358,7 @@ fn scrape_target_config(config: &Config, triple: &str)
     let mut ret = ops::TargetConfig {
         ar: ar.map(|p| p.0),
         linker: linker.map(|p| p.0),
+        rustflags: Vec::new(),
         overrides: HashMap::new(),
     };
     let table = match try!(config.get_table(&key)) {
@@ -238,27 +366,60 @@ fn scrape_target_config(config: &Config, triple: &str)
         None => return Ok(ret),
     };
     for (lib_name, _) in table.into_iter() {
-        if lib_name == "ar" || lib_name == "linker" { continue }
+        if lib_name == "ar" || lib_name == "linker" || lib_name == "rustflags" {
+            continue
+        }
 
         let mut output = BuildOutput {
             library_paths: Vec::new(),
             library_links: Vec::new(),
+            cfgs: Vec::new(),
+            env: Vec::new(),
             metadata: Vec::new(),
+            warnings: Vec::new(),
+            rerun_if_changed: Vec::new(),
+            rerun_if_env_changed: Vec::new(),
         };
         let key = format!("{}.{}", key, lib_name);
         let table = try!(config.get_table(&key)).unwrap().0;
-        for (k, _) in table.into_iter() {
+        for (k, value) in table.into_iter() {
             let key = format!("{}.{}", key, k);
-            let (v, path) = try!(config.get_string(&key)).unwrap();
-            if k == "rustc-flags" {
-                let whence = format!("in `{}` (in {:?})", key, path);
-                let (paths, links) = try!(
-                    BuildOutput::parse_rustc_flags(&v, &whence)
-                );
-                output.library_paths.extend(paths.into_iter());
-                output.library_links.extend(links.into_iter());
-            } else {
-                output.metadata.push((k, v));
+            match &k[..] {
+                "rustc-flags" => {
+                    let (v, path) = try!(config.get_string(&key)).unwrap();
+                    let whence = format!("in `{}` (in {:?})", key, path);
+                    let (paths, links) = try!(
+                        BuildOutput::parse_rustc_flags(&v, &whence)
+                    );
+                    output.library_paths.extend(paths.into_iter());
+                    output.library_links.extend(links.into_iter());
+                }
+                "rustc-link-lib" => {
+                    let list = try!(string_list(&value, &key));
+                    output.library_links.extend(list.into_iter());
+                }
+                "rustc-link-search" => {
+                    let list = try!(string_list(&value, &key));
+                    output.library_paths.extend(list.iter().map(|s| {
+                        PathBuf::new(s)
+                    }));
+                }
+                "rustc-cfg" => {
+                    let list = try!(string_list(&value, &key));
+                    output.cfgs.extend(list.into_iter());
+                }
+                "rustc-env" => {
+                    let (env, _) = try!(config.get_table(&key)).unwrap();
+                    for (name, _) in env.into_iter() {
+                        let var = format!("{}.{}", key, name);
+                        let (v, _) = try!(config.get_string(&var)).unwrap();
+                        output.env.push((name, v));
+                    }
+                }
+                _ => {
+                    let (v, _) = try!(config.get_string(&key)).unwrap();
+                    output.metadata.push((k, v));
+                }
             }
         }
         ret.overrides.insert(lib_name, output);
@@ -266,3 +427,10 @@ fn scrape_target_config(config: &Config, triple: &str)
 
     Ok(ret)
 }
+
+fn string_list(value: &ConfigValue, key: &str) -> CargoResult<Vec<String>> {
+    let list = try!(value.list().chain_error(|| {
+        human(format!("invalid configuration for the key `{}`", key))
+    }));
+    Ok(list.iter().map(|&(ref s, _)| s.clone()).collect())
+}
@@ -1,16 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+use semver::{self, VersionReq};
 
 use log::LogLevel::*;
 
-use std::io::Write;
-use std::path::Path;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
 use std::fs;
 use std::fs::{File, PathExt};
 
-use core::{Source, dependency};
+use core::{Package, PackageId, Source, Summary, Target, Workspace, dependency};
+use core::registry::PackageRegistry;
+use core::resolver::{Method, Resolve, ResolveOptions};
+use ops;
 use sources::{PathSource};
 use util::config::Config;
-use util::{CargoResult, human};
+use util::{CargoResult, ChainError, human, unified_diff};
 
 use debian::package::{Changelog, ChangelogEntry,
                       Dependency, SingleDependency, VRel,
@@ -20,8 +25,27 @@ use debian::package::{Changelog, ChangelogEntry,
                       parse_dep_list};
 use debian::Version;
 
+/// Where Debian installs the rlibs and dylibs of packaged crates, as a
+/// pattern matching any architecture and rustc version. Suitable for
+/// `.install` files.
+const DEB_RUSTLIB_GLOB: &'static str = "/usr/lib/*/rust/*/lib/rustlib/*/lib";
+
 pub struct DebianizeOptions<'a, 'b: 'a> {
-    pub config: &'a Config<'b>
+    pub config: &'a Config<'b>,
+    pub target: Option<&'a str>,
+    pub bump_revision: bool,
+    pub feature_packages: bool,
+    /// Fail instead of writing anything if some file would change.
+    pub dry_run: bool,
+    /// Print the changes as a unified diff instead of writing them.
+    pub diff: bool,
+    /// Also package every dependency not yet known to the archive.
+    pub recursive: bool,
+    /// A Packages file listing the crates already available in the archive.
+    pub packages_file: Option<&'a Path>,
+    /// Where the sources of the dependencies get unpacked and debianized,
+    /// defaults to `target/debian` of the root package.
+    pub output_dir: Option<&'a Path>,
 }
 
 pub fn xform_pkg_name(cargo_name: &str) -> String {
@@ -49,6 +73,409 @@ pub fn xform_pkg_name(cargo_name: &str) -> String {
     };
 }
 
+/// Turns a Cargo name into a valid part of a Debian package name, which may
+/// only contain lowercase letters, digits and `+-.`.
+fn deb_name(cargo_name: &str) -> String {
+    cargo_name.replace("_", "-").to_lowercase()
+}
+
+/// Splits a Debian version string into its epoch, upstream version and
+/// Debian revision parts, i.e. `[epoch:]upstream[-revision]`.
+fn split_dpkg_version(version: &str) -> (Option<&str>, &str, Option<&str>) {
+    let (epoch, rest) = match version.find(':') {
+        Some(idx) => (Some(&version[..idx]), &version[idx + 1..]),
+        None => (None, version)
+    };
+    match rest.rfind('-') {
+        Some(idx) => (epoch, &rest[..idx], Some(&rest[idx + 1..])),
+        None => (epoch, rest, None)
+    }
+}
+
+/// Increments the trailing number of a Debian revision, so that `1` becomes
+/// `2` and `0ubuntu1` becomes `0ubuntu2`. A revision without a trailing
+/// number gets one appended.
+fn bump_dpkg_revision(revision: &str) -> String {
+    let prefix = revision.trim_right_matches(|c: char| c.is_digit(10));
+    match revision[prefix.len()..].parse::<u32>() {
+        Ok(n) => format!("{}{}", prefix, n + 1),
+        Err(..) => format!("{}1", revision)
+    }
+}
+
+/// Converts a (possibly partial) semver version to Debian version syntax.
+/// Pre-release tags are introduced with a tilde, so that they sort before
+/// the release itself.
+fn deb_version(major: u64, minor: Option<u64>, patch: Option<u64>,
+               pre: Option<&str>) -> String {
+    let mut v = format!("{}.{}.{}", major, minor.unwrap_or(0),
+                        patch.unwrap_or(0));
+    if let Some(pre) = pre {
+        v.push('~');
+        v.push_str(pre);
+    }
+    v
+}
+
+/// Converts the version of a package to the upstream part of its Debian
+/// version, using the same pre-release mapping as version relations.
+fn deb_upstream_version(version: &semver::Version) -> String {
+    let pre = version.pre.iter().map(|p| p.to_string())
+                     .collect::<Vec<_>>().connect(".");
+    let pre = if pre.is_empty() { None } else { Some(&pre[..]) };
+    deb_version(version.major, Some(version.minor), Some(version.patch), pre)
+}
+
+/// Translates a Cargo version requirement into a list of Debian version
+/// relations, all of which have to hold. Caret, tilde, wildcard and exact
+/// requirements are split into a lower and an upper bound.
+fn deb_version_relations(req: &VersionReq)
+                         -> CargoResult<Vec<(VRel, Version)>> {
+    let req = req.to_string();
+    let unsupported = || human(format!("unsupported version requirement `{}`",
+                                       req));
+    let mut bounds = vec![];
+    for pred in req.split(',').map(|p| p.trim()) {
+        let (op, rest) = match ["^", "~", ">=", "<=", ">", "<", "="].iter()
+                                   .find(|op| pred.starts_with(**op)) {
+            Some(op) => (*op, pred[op.len()..].trim()),
+            None => ("^", pred)
+        };
+        let rest = rest.splitn(1, '+').next().unwrap();
+        let mut parts = rest.splitn(1, '-');
+        let version = parts.next().unwrap();
+        let pre = parts.next();
+
+        let wildcard = version.ends_with("*");
+        let mut nums = vec![];
+        for n in version.split('.').filter(|n| *n != "*") {
+            nums.push(try!(n.parse::<u64>().map_err(|_| unsupported())));
+        }
+        if nums.is_empty() {
+            continue  // `*` matches everything
+        } else if nums.len() > 3 {
+            return Err(unsupported())
+        }
+        let major = nums[0];
+        let minor = nums.get(1).cloned();
+        let patch = nums.get(2).cloned();
+        let lower = deb_version(major, minor, patch, pre);
+
+        // The smallest version not matched anymore when bumping the last
+        // component given.
+        let next = match (minor, patch) {
+            (Some(minor), Some(patch)) => (major, minor, patch + 1),
+            (Some(minor), None) => (major, minor + 1, 0),
+            _ => (major + 1, 0, 0),
+        };
+        let upper = |(major, minor, patch): (u64, u64, u64)| {
+            format!("{}.{}.{}~", major, minor, patch)
+        };
+
+        match op {
+            _ if wildcard => {
+                bounds.push((VRel::GreaterOrEqual, lower));
+                bounds.push((VRel::Lesser, upper(next)));
+            }
+            "^" => {
+                let limit = match (major, minor, patch) {
+                    (0, Some(0), Some(patch)) => (0, 0, patch + 1),
+                    (0, Some(minor), _) => (0, minor + 1, 0),
+                    (major, _, _) => (major + 1, 0, 0),
+                };
+                bounds.push((VRel::GreaterOrEqual, lower));
+                bounds.push((VRel::Lesser, upper(limit)));
+            }
+            "~" => {
+                let limit = match minor {
+                    Some(minor) => (major, minor + 1, 0),
+                    None => (major + 1, 0, 0),
+                };
+                bounds.push((VRel::GreaterOrEqual, lower));
+                bounds.push((VRel::Lesser, upper(limit)));
+            }
+            "=" => {
+                bounds.push((VRel::GreaterOrEqual, lower));
+                bounds.push((VRel::Lesser, upper(next)));
+            }
+            ">=" => bounds.push((VRel::GreaterOrEqual, lower)),
+            ">" => bounds.push((VRel::GreaterOrEqual, upper(next))),
+            "<" => bounds.push((VRel::Lesser, lower)),
+            "<=" => bounds.push((VRel::Lesser, upper(next))),
+            _ => unreachable!(),
+        }
+    }
+
+    bounds.into_iter().map(|(rel, v)| {
+        match Version::parse(&v) {
+            Ok(v) => Ok((rel, v)),
+            Err(..) => Err(human(format!("invalid Debian version `{}`", v)))
+        }
+    }).collect()
+}
+
+/// Returns the Debian relations on the `-dev` package providing the
+/// dependency, versioned according to its Cargo version requirement.
+fn dev_package_relations(dep: &dependency::Dependency,
+                         arch: Option<Vec<String>>)
+                         -> CargoResult<Vec<Dependency>> {
+    let dev_name = format!("{}-dev", xform_pkg_name(dep.name()));
+    let relations = try!(deb_version_relations(dep.version_req()));
+    let versions = if relations.is_empty() {
+        vec![None]
+    } else {
+        relations.into_iter().map(|v| Some(v)).collect()
+    };
+    Ok(versions.into_iter().map(|version| {
+        Dependency { alternatives: vec![
+            SingleDependency {
+                package: dev_name.clone(),
+                version: version,
+                arch: arch.clone()
+            }
+        ]}
+    }).collect())
+}
+
+/// The Debian architectures rustc builds for, with the target triple the
+/// packages are built with on each of them.
+const DEB_ARCH_TRIPLES: &'static [(&'static str, &'static str)] = &[
+    ("amd64", "x86_64-unknown-linux-gnu"),
+    ("i386", "i686-unknown-linux-gnu"),
+    ("arm64", "aarch64-unknown-linux-gnu"),
+    ("armhf", "arm-unknown-linux-gnueabihf"),
+    ("armel", "arm-unknown-linux-gnueabi"),
+    ("mips", "mips-unknown-linux-gnu"),
+    ("mipsel", "mipsel-unknown-linux-gnu"),
+    ("powerpc", "powerpc-unknown-linux-gnu"),
+    ("ppc64", "powerpc64-unknown-linux-gnu"),
+    ("ppc64el", "powerpc64le-unknown-linux-gnu"),
+    ("s390x", "s390x-unknown-linux-gnu"),
+];
+
+/// Maps a rustc target triple to the Debian architecture it corresponds
+/// to. Only Linux triples are mapped, `None` is returned for any other
+/// operating system. CPUs Debian doesn't know turn into the `linux-any`
+/// wildcard.
+fn deb_arch_for_triple(triple: &str) -> Option<String> {
+    let mut parts = triple.split('-');
+    let cpu = match parts.next().unwrap_or("") {
+        "i386" | "i486" | "i586" => "i686",
+        "armv7" => "arm",
+        cpu => cpu,
+    };
+    let rest = parts.collect::<Vec<_>>();
+    if !rest.contains(&"linux") {
+        return None
+    }
+    let abi = rest.last().map(|s| *s).unwrap_or("");
+
+    // The ABI only tells apart the architectures sharing a CPU, like armel
+    // and armhf.
+    let candidates = DEB_ARCH_TRIPLES.iter().filter(|&&(_, t)| {
+        t.split('-').next() == Some(cpu)
+    }).collect::<Vec<_>>();
+    let arch = match candidates.len() {
+        0 => None,
+        1 => Some(candidates[0].0),
+        _ => candidates.iter().find(|&&&(_, t)| t.ends_with(abi))
+                       .map(|&&(arch, _)| arch),
+    };
+    Some(arch.unwrap_or("linux-any").to_string())
+}
+
+/// Maps the platform of a `[target.*]` section, a target triple or a
+/// `cfg(...)` expression, to the Debian architectures it covers: `None` for
+/// all of them, otherwise a list of either architectures or negated ones
+/// (`!armhf`). An empty list means none of them.
+fn deb_archs_for_platform(platform: &str) -> Option<Vec<String>> {
+    if !platform.starts_with("cfg(") || !platform.ends_with(")") {
+        return Some(deb_arch_for_triple(platform).into_iter().collect())
+    }
+    let mut cfg = platform[4..platform.len() - 1].trim();
+    let negated = cfg.starts_with("not(") && cfg.ends_with(")");
+    if negated {
+        cfg = cfg[4..cfg.len() - 1].trim();
+    }
+
+    let mut parts = cfg.splitn(1, '=').map(|s| s.trim());
+    let key = parts.next().unwrap();
+    let value = parts.next().map(|s| s.trim_matches('"'));
+    let archs = match (key, value) {
+        ("target_arch", Some(arch)) => {
+            DEB_ARCH_TRIPLES.iter().filter(|&&(_, triple)| {
+                let target_arch = match triple.split('-').next().unwrap() {
+                    "i686" => "x86",
+                    "powerpc64le" => "powerpc64",
+                    cpu => cpu,
+                };
+                target_arch == arch
+            }).map(|&(deb_arch, _)| {
+                if negated {format!("!{}", deb_arch)} else {deb_arch.to_string()}
+            }).collect()
+        }
+        ("target_os", Some("linux")) | ("unix", None) => {
+            if negated {Vec::new()} else {return None}
+        }
+        ("target_os", Some(_)) | ("windows", None) => {
+            if negated {return None} else {Vec::new()}
+        }
+        // Anything else can't be told apart by the architecture, the
+        // dependency is assumed to be needed everywhere.
+        _ => return None,
+    };
+    // Excluding no architecture at all leaves all of them.
+    if negated && archs.is_empty() { None } else { Some(archs) }
+}
+
+/// Computes the Debian architectures each dependency is needed on: `None`
+/// if it's needed everywhere, otherwise the list of architectures from the
+/// `[target.*]` sections it appears in, or of the architectures it isn't
+/// needed on, negated. An empty list means Debian never needs the
+/// dependency.
+fn dep_architectures(deps: &[dependency::Dependency])
+                     -> HashMap<String, Option<Vec<String>>> {
+    let mut ret: HashMap<String, Option<Vec<String>>> = HashMap::new();
+    for dep in deps.iter() {
+        let arch = match dep.only_for_platform() {
+            Some(p) => deb_archs_for_platform(p),
+            None => None,
+        };
+        let merged = match (ret.remove(dep.name()), arch) {
+            (Some(None), _) | (_, None) => None,
+            (None, Some(arch)) => Some(arch),
+            (Some(Some(cur)), Some(arch)) => merge_archs(cur, arch),
+        };
+        ret.insert(dep.name().to_string(), merged);
+    }
+    ret
+}
+
+// The union of two lists of architectures as returned by
+// `deb_archs_for_platform`.
+fn merge_archs(a: Vec<String>, b: Vec<String>) -> Option<Vec<String>> {
+    let is_negated = |list: &[String]| list.iter().any(|s| s.starts_with("!"));
+    let ret: Vec<String> = match (is_negated(&a[..]), is_negated(&b[..])) {
+        (false, false) => {
+            let mut ret = a;
+            for arch in b.into_iter() {
+                if !ret.contains(&arch) { ret.push(arch); }
+            }
+            return Some(ret)
+        }
+        // Only the architectures excluded by both stay excluded.
+        (true, true) => a.into_iter().filter(|s| b.contains(s)).collect(),
+        (true, false) => a.into_iter().filter(|s| {
+            !b.contains(&s[1..].to_string())
+        }).collect(),
+        (false, true) => b.into_iter().filter(|s| {
+            !a.contains(&s[1..].to_string())
+        }).collect(),
+    };
+    if ret.is_empty() { None } else { Some(ret) }
+}
+
+/// Computes the set of features turned on by enabling all of `features`,
+/// following features that enable other features. The names of optional
+/// dependencies pulled in are part of the set, just like Cargo handles it.
+fn features_closure(summary: &Summary, features: &[String]) -> HashSet<String> {
+    let mut ret = HashSet::new();
+    let mut todo = features.to_vec();
+    while let Some(feature) = todo.pop() {
+        // Features of dependencies (`dep/feature`) only enable `dep`.
+        let feature = feature.splitn(1, '/').next().unwrap().to_string();
+        if !ret.insert(feature.clone()) {
+            continue
+        }
+        if let Some(list) = summary.features().get(&feature) {
+            todo.extend(list.iter().cloned());
+        }
+    }
+    ret
+}
+
+/// Returns true if the relation is a lower bound on the version.
+fn is_lower_bound(rel: &VRel) -> bool {
+    match *rel {
+        VRel::GreaterOrEqual | VRel::Greater => true,
+        _ => false,
+    }
+}
+
+/// Returns the path to the crate root of `target`, relative to `cwd` where
+/// possible.
+// FIXME: maybe reunite with root_path from cargo_rustc/mod.rs, where this is
+// stolen from.
+fn relative_src_path(package: &Package, target: &Target, cwd: &Path) -> PathBuf {
+    let absolute = package.root().join(target.src_path());
+    if absolute.starts_with(cwd) {
+        absolute.relative_from(cwd).map(|s| s.to_path_buf()).unwrap_or(absolute)
+    } else {
+        absolute
+    }
+}
+
+/// Writes the generated files to disk. With `--diff` or `--dry-run` only
+/// reports how they differ from what is on disk instead.
+fn emit_files(files: &[(PathBuf, String)], cwd: &Path,
+              options: &DebianizeOptions) -> CargoResult<()> {
+    if !options.dry_run && !options.diff {
+        for &(ref path, ref contents) in files.iter() {
+            let parent = path.parent().unwrap();
+            if !parent.exists() {
+                match fs::create_dir_all(parent) {
+                    Ok(_) => {},
+                    Err(e) => return Err(human(
+                        format!("Unable to create {}: {}", parent.display(), e)))
+                }
+                debug!("Created {} - it didn't exist before.", parent.display());
+            }
+            let mut f = match File::create(path) {
+                Ok(f) => f,
+                Err(e) => return Err(human(e))
+            };
+            match f.write_all(contents.as_bytes()) {
+                Ok(_) => {},
+                Err(e) => return Err(human(e))
+            };
+        }
+        return Ok(())
+    }
+
+    let mut changed = vec![];
+    for &(ref path, ref contents) in files.iter() {
+        let name = path.relative_from(cwd).unwrap_or(path).display().to_string();
+        let (old, old_name) = if path.exists() {
+            let mut old = String::new();
+            match File::open(path).and_then(|mut f| f.read_to_string(&mut old)) {
+                Ok(_) => {},
+                Err(e) => return Err(human(e))
+            };
+            (Some(old), format!("a/{}", name))
+        } else {
+            (None, "/dev/null".to_string())
+        };
+
+        let old = old.as_ref().map(|s| &s[..]);
+        match unified_diff(old, contents, &old_name, &format!("b/{}", name)) {
+            Some(diff) => {
+                if options.diff {
+                    print!("{}", diff);
+                }
+                changed.push(name);
+            }
+            None => {}
+        }
+    }
+
+    if options.dry_run && !changed.is_empty() {
+        return Err(human(format!("The debian directory is out of date, \
+                                  these files would change:\n  {}",
+                                 changed.connect("\n  "))))
+    }
+    Ok(())
+}
+
 pub struct MakefileRule {
     target: String,
     deps: Vec<String>,
@@ -119,48 +546,51 @@ pub fn debianize(manifest_path: &Path,
     let cargo_repo = cargo_metadata.repository.clone();
     let cargo_desc = cargo_metadata.description.clone();
     let cargo_targets = package.targets();
+
+    // Only the default feature set decides what the packages depend on,
+    // optional dependencies outside of it are merely suggested.
+    let default_features = if package.summary().features().contains_key("default") {
+        features_closure(package.summary(), &["default".to_string()])
+    } else {
+        HashSet::new()
+    };
+    let is_enabled = |dep: &dependency::Dependency| {
+        !dep.is_optional() || default_features.contains(dep.name())
+    };
+    // Platform specific dependencies get Debian architecture restrictions.
+    let dep_archs = dep_architectures(package.dependencies());
+    let is_needed = |dep: &dependency::Dependency| {
+        dep_archs.get(dep.name()).map_or(true, |arch| {
+            arch.as_ref().map_or(true, |a| !a.is_empty())
+        })
+    };
+    let dep_arch = |dep: &dependency::Dependency| {
+        dep_archs.get(dep.name()).and_then(|a| a.clone())
+    };
+
+    let mut feature_cfgs = default_features.iter().map(|f| {
+        format!("--cfg 'feature=\"{}\"'", f)
+    }).collect::<Vec<String>>();
+    feature_cfgs.sort();
+    let feature_cfgs = feature_cfgs.connect(" ");
     
     let dpkg_source_name = xform_pkg_name(package.name());
-    let dpkg_version = package.version().to_string() + "-1";
+    let upstream_version = deb_upstream_version(package.version());
+    let dpkg_version = format!("{}-1", upstream_version);
 
     let deb_dir = manifest_path.parent().unwrap().join("debian");
 
-    // Create the 'debian' directory, if it doesn't exist. Otherwise
-    // check if it's a directory.
-    if !deb_dir.exists() {
-        match fs::create_dir(&deb_dir) {
-            Ok(_) => {},
-            Err(e) => return Err(human(
-                format!("Unable to create the debian directory: {}", e)
-                    .to_string()))
-        }
-        debug!("Created the 'debian' directory - it didn't exist before.");
-    } else if !deb_dir.is_dir() {
+    // The 'debian' directory gets created along with the first file
+    // written to it, if it doesn't exist. Otherwise check if it's a
+    // directory.
+    if deb_dir.exists() && !deb_dir.is_dir() {
         return Err(human(
             format!("Expected a directory, but {} is a file.",
                     deb_dir.display())));
     }
 
-    // Update or create 'debian/changelog'.
-    let deb_changelog = {
-        let mut x = deb_dir.clone();
-        x.push("changelog");
-        x
-    };
-
-    if deb_changelog.exists() {
-        panic!("Updating changelog not implemented, yet.");
-    } else {
-        let detail = "  * Initial debianization by cargo.\n".to_string();
-
-        let e = ChangelogEntry::new(dpkg_source_name.clone(),
-                                    dpkg_version, detail);
-        let changelog = Changelog::new(e);
-        match changelog.to_file(&deb_changelog) {
-            Ok(_) => {},
-            Err(e) => return Err(human(e))
-        };
-    }
+    // Everything generated is kept in memory until all of it is known.
+    let mut files: Vec<(PathBuf, String)> = vec![];
 
     let deb_control = {
         let mut x = deb_dir.clone();
@@ -214,7 +644,9 @@ pub fn debianize(manifest_path: &Path,
         gp.add_entry("Standards-Version", "3.9.6".to_string());
     }
 
-    // Synchronize Build-Depends
+    // Synchronize Build-Depends, remembering what changed for the
+    // changelog.
+    let mut dep_changes = vec![];
     {
         let cur_bd = match gp.get_entry("Build-Depends") {
             Some(val) => match parse_dep_list(val) {
@@ -263,7 +695,9 @@ pub fn debianize(manifest_path: &Path,
             new_bd.push(dep);
         }
 
-        // Then, check against the dependencies from Cargo.
+        // Then, check against the dependencies from Cargo. Dependencies
+        // for several platforms appear once per platform.
+        let mut seen_deps = HashSet::new();
         for dep in package.dependencies().iter() {
             let deb_name = xform_pkg_name(dep.name());
             debug!("  dependency: {} - dpkg: {}", dep.name(), deb_name);
@@ -272,6 +706,17 @@ pub fn debianize(manifest_path: &Path,
                 debug!("     optional");
             }
 
+            if !is_enabled(dep) {
+                debug!("     not enabled by default, skipping");
+                continue;
+            }
+
+            if !is_needed(dep) {
+                debug!("     not needed on any Debian architecture, skipping");
+                continue;
+            }
+            let arch = dep_arch(dep);
+
             match dep.kind() {
                 dependency::Kind::Normal => debug!("      normal dep"),
                 dependency::Kind::Development => debug!("      development dep"),
@@ -294,19 +739,68 @@ pub fn debianize(manifest_path: &Path,
             }
             
 
-            match dep_map.get(&deb_name) {
-                Some(dep) => {
-                    debug!("Already contains build dependency {}: {:?}.", deb_name, dep);
+            let dev_name = format!("{}-dev", deb_name);
+            if !seen_deps.insert(dev_name.clone()) {
+                continue;
+            }
+            match dep_map.get(&dev_name) {
+                Some(cur) => {
+                    debug!("Already contains build dependency {}: {:?}.", deb_name, cur);
+
+                    // Only entries without alternatives are ever updated.
+                    let existing = new_bd.iter().filter(|d| {
+                        d.alternatives.len() == 1 &&
+                            d.alternatives[0].package == dev_name
+                    }).filter_map(|d| {
+                        d.alternatives[0].version.clone()
+                    }).collect::<Vec<_>>();
+
+                    // Keep every existing bound that is at least as strict
+                    // as the one derived from Cargo.
+                    let relations = try!(deb_version_relations(dep.version_req()));
+                    let mut changed = false;
+                    let mut merged = vec![];
+                    for (rel, v) in relations.into_iter() {
+                        let lower = is_lower_bound(&rel);
+                        match existing.iter().find(|&&(ref r, _)| {
+                            is_lower_bound(r) == lower
+                        }) {
+                            Some(&(_, ref cur)) if (lower && *cur >= v) ||
+                                                   (!lower && *cur <= v) => {}
+                            _ => { changed = true; merged.push((rel, v)); }
+                        }
+                    }
+                    if !changed {
+                        continue;
+                    }
+
+                    for &(ref rel, ref v) in existing.iter() {
+                        if !merged.iter().any(|&(ref r, _)| {
+                            is_lower_bound(r) == is_lower_bound(rel)
+                        }) {
+                            merged.push((rel.clone(), v.clone()));
+                        }
+                    }
+                    dep_changes.push(format!("Update build dependency on {}.",
+                                             dev_name));
+                    new_bd.retain(|d| {
+                        d.alternatives.len() != 1 ||
+                            d.alternatives[0].package != dev_name
+                    });
+                    for version in merged.into_iter() {
+                        new_bd.push(Dependency { alternatives: vec![
+                            SingleDependency {
+                                package: dev_name.clone(),
+                                version: Some(version),
+                                arch: arch.clone()
+                            }
+                        ]});
+                    }
                 },
                 None => {
-                    let dep = Dependency { alternatives: vec![
-                        SingleDependency {
-                            package: format!("{}-dev", deb_name),
-                            version: None,
-                            arch: None
-                        }
-                    ]};
-                    new_bd.push(dep);
+                    dep_changes.push(format!("Add build dependency on {}.",
+                                             dev_name));
+                    new_bd.extend(try!(dev_package_relations(dep, arch)).into_iter());
                 }
             }
         }
@@ -317,6 +811,57 @@ pub fn debianize(manifest_path: &Path,
                         .connect(", "));
     }
 
+    // Update or create 'debian/changelog'.
+    let deb_changelog = deb_dir.join("changelog");
+    if deb_changelog.exists() {
+        let mut changelog = match Changelog::from_file(&deb_changelog) {
+            Ok(c) => c,
+            Err(e) => return Err(human(e))
+        };
+
+        let last_version = match changelog.get_entries().get(0) {
+            Some(entry) => entry.version.to_string(),
+            None => return Err(human(format!("{} does not contain any entries",
+                                             deb_changelog.display())))
+        };
+        let (epoch, upstream, revision) = split_dpkg_version(&last_version);
+        let epoch = epoch.map(|e| format!("{}:", e)).unwrap_or(String::new());
+        debug!("last changelog version: {}", last_version);
+
+        let mut detail = String::new();
+        let new_version = if upstream != upstream_version {
+            detail.push_str(&format!("  * New upstream release {}.\n",
+                                     package.version()));
+            Some(format!("{}{}", epoch, dpkg_version))
+        } else if options.bump_revision {
+            detail.push_str("  * Rebuild.\n");
+            Some(format!("{}{}-{}", epoch, upstream_version,
+                         bump_dpkg_revision(revision.unwrap_or("0"))))
+        } else {
+            None
+        };
+
+        match new_version {
+            Some(version) => {
+                for change in dep_changes.iter() {
+                    detail.push_str(&format!("  * {}\n", change));
+                }
+                let e = ChangelogEntry::new(dpkg_source_name.clone(),
+                                            version, detail);
+                changelog.add_entry(e);
+                files.push((deb_changelog, changelog.to_string()));
+            }
+            None => debug!("changelog is up to date, leaving it alone")
+        }
+    } else {
+        let detail = "  * Initial debianization by cargo.\n".to_string();
+
+        let e = ChangelogEntry::new(dpkg_source_name.clone(),
+                                    dpkg_version, detail);
+        let changelog = Changelog::new(e);
+        files.push((deb_changelog, changelog.to_string()));
+    }
+
     // We always override repository and homepage info.
     match cargo_repo {
         Some(val) => {
@@ -341,36 +886,160 @@ pub fn debianize(manifest_path: &Path,
 
 
 
-    let /* mut */ release_targets = cargo_targets.iter()
+    let release_targets = cargo_targets.iter()
         .filter(|target| target.profile().env() == "release");
 
+    let long_desc = match &cargo_desc {
+        &Some(ref s) => Some(s.trim().split('\n')
+                             .map(|s| s.to_string())
+                             .collect::<Vec<String>>()
+                             .connect("\n ")),
+        &None => None
+    };
 
+    let cwd = manifest_path.parent().unwrap();
     let mut mk_rules = vec![];
+
+    // Figure out how to link against the dependencies. Path dependencies
+    // are built from their own Makefile.cargo before this package, all
+    // others must already be installed from their Debian package. Either
+    // way the rlib of their lib target is what gets linked.
+    let lib_names = try!(dep_lib_names(package, options.config));
+    let mut dep_stamps = vec![];
+    let mut ld_library_path = vec!["build".to_string()];
+    let mut dep_externs = vec!["-L $(RUSTLIB_DIR)".to_string()];
+    let mut seen_deps = HashSet::new();
+    for dep in package.dependencies().iter().filter(|d| {
+        d.kind() == dependency::Kind::Normal && is_enabled(*d) &&
+            is_needed(*d)
+    }) {
+        if !seen_deps.insert(dep.name()) {
+            continue;
+        }
+        let lib_name = try!(lib_names.get(dep.name()).chain_error(|| {
+            human(format!("dependency `{}` does not have a lib target",
+                          dep.name()))
+        }));
+        let extern_arg = if dep.source_id().is_path() {
+            let path = match dep.source_id().url().to_file_path() {
+                Ok(p) => p,
+                Err(..) => return Err(human(format!(
+                    "invalid path for dependency `{}`: {}", dep.name(),
+                    dep.source_id().url())))
+            };
+            let path = if path.starts_with(cwd) {
+                path.relative_from(cwd).map(|s| s.to_path_buf()).unwrap_or(path)
+            } else {
+                path
+            };
+
+            let stamp = format!("{}/build/lib{}.stamp", path.display(),
+                                lib_name);
+            let mut r = MakefileRule::new(stamp.clone());
+            r.add_rule(format!("$(MAKE) -C {} -f debian/Makefile.cargo all",
+                               path.display()));
+            mk_rules.push(r);
+            dep_stamps.push(stamp);
+            ld_library_path.push(format!("{}/build", path.display()));
+
+            format!("-L {0}/build --extern {1}=$(firstword $(wildcard {0}/build/lib{1}-*.rlib))",
+                    path.display(), lib_name)
+        } else {
+            format!("--extern {0}=$(firstword $(wildcard $(RUSTLIB_DIR)/lib{0}-*.rlib))",
+                    lib_name)
+        };
+
+        // Only link against platform specific dependencies when building
+        // for one of their architectures.
+        match dep_archs.get(dep.name()).and_then(|a| a.as_ref()) {
+            Some(archs) if archs.iter().any(|a| a.starts_with("!")) => {
+                let archs = archs.iter().map(|a| &a[1..]).collect::<Vec<_>>();
+                dep_externs.push(format!("$(if $(shell for a in {}; do dpkg-architecture -i$$a && echo y && break; done),,{})",
+                                         archs.connect(" "), extern_arg));
+            }
+            Some(archs) => {
+                dep_externs.push(format!("$(if $(shell for a in {}; do dpkg-architecture -i$$a && echo y && break; done),{})",
+                                         archs.connect(" "), extern_arg));
+            }
+            None => dep_externs.push(extern_arg)
+        }
+    }
+    let dep_externs = dep_externs.connect(" ");
+    let ld_library_path = ld_library_path.connect(":");
+
+    // The -dev package depends on the -dev packages of the dependencies
+    // enabled by default. Optional ones are only suggested or, on request,
+    // pulled in by a separate -dev package per feature. Platform specific
+    // dependencies are qualified with the architectures they are needed on.
+    let mut dev_depends = vec!["${misc:Depends}".to_string(),
+                               "${shlibs:Depends}".to_string()];
+    let mut dev_suggests = vec![];
+    for dep in package.dependencies().iter().filter(|d| {
+        d.kind() == dependency::Kind::Normal && is_needed(*d)
+    }) {
+        let relations = try!(dev_package_relations(dep, dep_arch(dep))).iter()
+                            .map(|d| d.to_string()).collect::<Vec<_>>();
+        let list = if is_enabled(dep) {
+            &mut dev_depends
+        } else if !options.feature_packages {
+            &mut dev_suggests
+        } else {
+            continue
+        };
+        for relation in relations.into_iter() {
+            if !list.contains(&relation) { list.push(relation); }
+        }
+    }
+
+    let mut feature_names = package.summary().features().keys()
+        .filter(|f| !default_features.contains(*f))
+        .cloned().collect::<Vec<String>>();
+    feature_names.sort();
+    let mut feature_pkgs = vec![];
+    for feature in feature_names.into_iter() {
+        let enabled = features_closure(package.summary(), &[feature.clone()]);
+        let mut depends = vec![];
+        for dep in package.dependencies().iter().filter(|d| {
+            d.kind() == dependency::Kind::Normal && !is_enabled(*d) &&
+                is_needed(*d) && enabled.contains(d.name())
+        }) {
+            for relation in try!(dev_package_relations(dep, dep_arch(dep))).iter() {
+                let relation = relation.to_string();
+                if !depends.contains(&relation) { depends.push(relation); }
+            }
+        }
+        let pkg_name = format!("{}+{}-dev", dpkg_source_name,
+                               deb_name(feature));
+        if options.feature_packages {
+            dev_suggests.push(pkg_name.clone());
+        }
+        feature_pkgs.push((feature, pkg_name, depends));
+    }
+
     let mut target_libs = vec![]; // libs to install
+    let mut target_bins = vec![]; // binaries to install
+    let mut lib_stamps = vec![];  // what binaries need to link against
+    let mut lib_externs = vec![]; // --extern flags for the built libs
+    let mut lib_pkg = None;       // the binary package of the dylib
     let mut all_targets = vec![]; // what 'all' needs to build
     for target in cargo_targets.iter().filter(|tgt|
             tgt.is_lib() && tgt.profile().env() == "release") {
         let metadata = target.metadata().unwrap();
         let stamp = "build/lib".to_string() + target.name() + ".stamp";
-
-        // FIXME: maybe reunite with root_path from
-        // cargo_rustc/mod.rs, where this is stolen from.
-        let absolute = package.root().join(target.src_path());
-        let cwd = manifest_path.parent().unwrap();
-        let crate_src_path = if absolute.starts_with(cwd) {
-            absolute.relative_from(cwd).map(|s| s.to_path_buf()).unwrap_or(absolute)
-        } else {
-            absolute
-        };
+        let crate_src_path = relative_src_path(package, target, cwd);
 
         let mut r = MakefileRule::new(stamp.clone());
-        // fixme: dependencies
+        for dep in dep_stamps.iter() {
+            r.add_dep(dep.clone());
+        }
         r.add_rule("@if test ! -d build; then mkdir build; fi".to_string());
-        r.add_rule(format!("rustc {} --crate-name {} --crate-type staticlib,rlib,dylib -C prefer-dynamic -C opt-level=3 --cfg ndebug -C metadata={} -C extra-filename={} --out-dir build --emit=dep-info,link",
+        r.add_rule(format!("rustc {} --crate-name {} --crate-type staticlib,rlib,dylib --target $(RUST_TARGET) -C prefer-dynamic -C opt-level=3 --cfg ndebug {} -C metadata={} -C extra-filename={} {} --out-dir build --emit=dep-info,link",
                           crate_src_path.display(),
                           target.name(),
+                          feature_cfgs,
                           metadata.metadata,
-                          metadata.extra_filename
+                          metadata.extra_filename,
+                          dep_externs
                            ));
         r.add_rule(format!("touch {}", stamp.clone()));
         mk_rules.push(r);
@@ -379,6 +1048,8 @@ pub fn debianize(manifest_path: &Path,
             metadata.extra_filename.as_slice() + ".so";
         mk_rules.push(MakefileRule::singleton(dylib_filename.clone(),
                                               stamp.clone()));
+        lib_externs.push(format!("--extern {}={}", target.name(),
+                                 dylib_filename));
         target_libs.push(dylib_filename);
 
         let rlib_filename = "build/lib".to_string() + target.name() +
@@ -393,23 +1064,16 @@ pub fn debianize(manifest_path: &Path,
                                               stamp.clone()));
         target_libs.push(staticlib_filename);
 
+        lib_stamps.push(stamp.clone());
         all_targets.push(stamp);
 
         // Add control paragraphs for the dylib and a separate -dev
         // package with the rlib and the static library.
-        let long_desc = match &cargo_desc {
-            &Some(ref s) => Some(s.trim().split('\n')
-                                 .map(|s| s.to_string())
-                                 .collect::<Vec<String>>()
-                                 .connect("\n ")),
-            &None => None
-        };
-        
+        let dylib_pkg = format!("{}-{}", dpkg_source_name, package.version());
         let mut lp = ControlParagraph::new();
-        lp.add_entry("Package",
-                     format!("{}-{}", dpkg_source_name,
-                             package.version()));
-        lp.add_entry("Architecture", "amd64 i386".to_string());
+        lp.add_entry("Package", dylib_pkg.clone());
+        lp.add_entry("Architecture", "any".to_string());
+        lp.add_entry("Multi-Arch", "same".to_string());
         lp.add_entry("Pre-Depends", "${misc:Pre-Depends}".to_string());
         lp.add_entry("Depends",
                      "${misc:Depends}, ${shlibs:Depends}".to_string());
@@ -426,11 +1090,13 @@ pub fn debianize(manifest_path: &Path,
 
         let mut lp = ControlParagraph::new();
         lp.add_entry("Package", dpkg_source_name.clone() + "-dev");
-        lp.add_entry("Architecture", "amd64 i386".to_string());
+        lp.add_entry("Architecture", "any".to_string());
+        lp.add_entry("Multi-Arch", "same".to_string());
         lp.add_entry("Pre-Depends", "${misc:Pre-Depends}".to_string());
-        lp.add_entry("Depends",
-                     "${misc:Depends}, ${shlibs:Depends}".to_string());
-        // Recommends, Suggests ??
+        lp.add_entry("Depends", dev_depends.connect(", "));
+        if !dev_suggests.is_empty() {
+            lp.add_entry("Suggests", dev_suggests.connect(", "));
+        }
 
         lp.add_entry("Description", dpkg_source_name.clone() +
                      "rust crate - rlib and staticlib" +
@@ -441,49 +1107,171 @@ pub fn debianize(manifest_path: &Path,
                      }.as_slice());
         cf.add_paragraph(lp);
 
+        if options.feature_packages {
+            for &(ref feature, ref pkg_name, ref depends) in feature_pkgs.iter() {
+                let mut fp = ControlParagraph::new();
+                fp.add_entry("Package", pkg_name.clone());
+                fp.add_entry("Architecture", "any".to_string());
+                fp.add_entry("Multi-Arch", "same".to_string());
+                let mut fp_depends = vec![
+                    "${misc:Depends}".to_string(),
+                    format!("{}-dev (= ${{binary:Version}})", dpkg_source_name)
+                ];
+                fp_depends.extend(depends.iter().cloned());
+                fp.add_entry("Depends", fp_depends.connect(", "));
+                fp.add_entry("Description", format!(
+                    "{} rust crate - feature \"{}\"\n \
+                     This metapackage pulls in the dependencies needed to use \
+                     the\n \"{}\" feature of the crate.",
+                    dpkg_source_name, feature, feature));
+                cf.add_paragraph(fp);
+            }
+        }
+
 
         // Generate .install files
         let deb_lib_install = deb_dir.join(&format!("{}-{}.install",
                                                     dpkg_source_name,
                                                     package.version())[..]);
-        {
-            let mut f = match File::create(&deb_lib_install) {
-                Ok(f) => f,
-                Err(e) => return Err(human(e))
-            };
-
-            mk_rules.reverse();
-            match f.write(format!("/usr/lib/x86_64-linux-gnu/rust/1.0/lib/rustlib/x86_64-unknown-linux-gnu/lib/lib{}-*.so\n", target.name()).as_bytes()) {
-                Ok(_) => {},
-                Err(e) => return Err(human(e))
-            };
-        }
+        files.push((deb_lib_install,
+                    format!("{}/lib{}-*.so\n", DEB_RUSTLIB_GLOB,
+                            target.name())));
 
         let deb_dev_install = deb_dir.join(&format!("{}-dev.install",
                                                     dpkg_source_name)[..]);
-        {
-            let mut f = match File::create(&deb_dev_install) {
-                Ok(f) => f,
-                Err(e) => return Err(human(e))
-            };
+        files.push((deb_dev_install,
+                    format!("{0}/lib{1}-*.rlib\n{0}/lib{1}-*.a\n",
+                            DEB_RUSTLIB_GLOB, target.name())));
 
-            mk_rules.reverse();
-            match f.write(format!("/usr/lib/x86_64-linux-gnu/rust/1.0/lib/rustlib/x86_64-unknown-linux-gnu/lib/lib{}-*.rlib\n/usr/lib/x86_64-linux-gnu/rust/1.0/lib/rustlib/x86_64-unknown-linux-gnu/lib/lib{}-*.a\n", target.name(), target.name()).as_bytes()) {
-                Ok(_) => {},
-                Err(e) => return Err(human(e))
-            };
+        lib_pkg = Some(dylib_pkg);
+    }
+
+    for target in release_targets {
+        debug!("tgt name: {}, src path: {:?}, metadata: {:?}, profile: {:?}", target.name(),
+               target.src_path(), target.metadata(), target.profile());
+
+        if target.is_lib() {
+            // Libraries are handled above.
+        } else if target.is_bin() {
+            // Link each binary dynamically against the library built from
+            // the same package, if any.
+            let bin_filename = "build/".to_string() + target.name();
+            let crate_src_path = relative_src_path(package, target, cwd);
+
+            let mut r = MakefileRule::new(bin_filename.clone());
+            for stamp in dep_stamps.iter().chain(lib_stamps.iter()) {
+                r.add_dep(stamp.clone());
+            }
+            r.add_rule("@if test ! -d build; then mkdir build; fi".to_string());
+            r.add_rule(format!("rustc {} --crate-name {} --crate-type bin --target $(RUST_TARGET) -C prefer-dynamic -C opt-level=3 --cfg ndebug {} {} -L build {} --out-dir build --emit=dep-info,link",
+                               crate_src_path.display(),
+                               target.name(),
+                               feature_cfgs,
+                               dep_externs,
+                               lib_externs.connect(" ")));
+            mk_rules.push(r);
+            target_bins.push(bin_filename.clone());
+            all_targets.push(bin_filename);
+
+            let bin_pkg = deb_name(target.name());
+            let mut bp = ControlParagraph::new();
+            bp.add_entry("Package", bin_pkg.clone());
+            bp.add_entry("Architecture", "any".to_string());
+            bp.add_entry("Pre-Depends", "${misc:Pre-Depends}".to_string());
+            bp.add_entry("Depends", match lib_pkg {
+                Some(ref p) => format!("${{misc:Depends}}, ${{shlibs:Depends}}, \
+                                        {} (= ${{binary:Version}})", p),
+                None => "${misc:Depends}, ${shlibs:Depends}".to_string()
+            });
+            bp.add_entry("Description", target.name().to_string() +
+                         " - rust binary" +
+                         match &long_desc {
+                             &Some(ref s) => ("\n ".to_string() + s.as_slice() +
+                         "\n .\n This package contains the binary."),
+                             &None => "".to_string()
+                         }.as_slice());
+            cf.add_paragraph(bp);
+
+            let deb_bin_install = deb_dir.join(&format!("{}.install",
+                                                        bin_pkg)[..]);
+            files.push((deb_bin_install,
+                        format!("/usr/bin/{}\n", target.name())));
+        } else if target.is_example() {
+            // Examples are not shipped in any binary package.
+        } else {
+            unreachable!();
         }
     }
 
 
-    // Add a 'check' target - FIXME: not currently functional
+    // Build the unit and integration tests, just like `cargo test` would.
+    let mut test_bins = vec![];
+    for target in cargo_targets.iter().filter(|tgt|
+            tgt.profile().env() == "test" && tgt.profile().is_test()) {
+        let extra_filename = target.metadata().map(|m| m.extra_filename.clone())
+                                   .unwrap_or(String::new());
+        let test_filename = format!("build/test/{}{}", target.name(),
+                                    extra_filename);
+        let crate_src_path = relative_src_path(package, target, cwd);
+
+        let mut args = vec![crate_src_path.display().to_string(),
+                            format!("--crate-name {}", target.name())];
+        if target.profile().uses_test_harness() {
+            args.push("--test".to_string());
+        } else {
+            args.push("--crate-type bin".to_string());
+        }
+        args.push("--target $(RUST_TARGET) -C prefer-dynamic -g".to_string());
+        args.push(feature_cfgs.clone());
+        args.push(dep_externs.clone());
+        args.push("-L build".to_string());
+        // Unit tests of the library are built from the library's sources,
+        // everything else links against the library.
+        if !target.is_lib() {
+            args.push(lib_externs.connect(" "));
+        }
+        if let Some(m) = target.metadata() {
+            args.push(format!("-C metadata={} -C extra-filename={}",
+                              m.metadata, m.extra_filename));
+        }
+
+        let mut r = MakefileRule::new(test_filename.clone());
+        for stamp in dep_stamps.iter().chain(lib_stamps.iter()) {
+            r.add_dep(stamp.clone());
+        }
+        r.add_rule("@if test ! -d build/test; then mkdir -p build/test; fi".to_string());
+        r.add_rule(format!("rustc {} --out-dir build/test --emit=dep-info,link",
+                           args.connect(" ")));
+        mk_rules.push(r);
+        test_bins.push(test_filename);
+    }
+
+    // Add a 'check' target running all tests and doc tests, unless
+    // DEB_BUILD_OPTIONS contains 'nocheck'.
     {
-        let mut r = MakefileRule::new("check".to_string());
+        let mut r = MakefileRule::new("run-tests".to_string());
         r.add_dep("all".to_string());
-        // r.add_dep("test_programs".to_string());
-
-        // FIXME: actually run the tests
+        for test in test_bins.into_iter() {
+            r.add_dep(test.clone());
+            r.add_rule(format!("LD_LIBRARY_PATH={} {}", ld_library_path, test));
+        }
+        for target in cargo_targets.iter().filter(|tgt|
+                tgt.is_lib() && tgt.profile().is_doctest()) {
+            let crate_src_path = relative_src_path(package, target, cwd);
+            r.add_rule(format!("LD_LIBRARY_PATH={} rustdoc --test {} --crate-name {} {} {} -L build {}",
+                               ld_library_path,
+                               crate_src_path.display(),
+                               target.name(),
+                               feature_cfgs,
+                               dep_externs,
+                               lib_externs.connect(" ")));
+        }
+        mk_rules.push(r);
 
+        let r = MakefileRule::singleton(
+            "check".to_string(),
+            "$(if $(filter nocheck,$(DEB_BUILD_OPTIONS)),all,run-tests)"
+                .to_string());
         mk_rules.push(r);
     }
 
@@ -493,9 +1281,16 @@ pub fn debianize(manifest_path: &Path,
     {
         let mut r = MakefileRule::new("install".to_string());
         r.add_dep("all".to_string());
-        r.add_rule("install -d $(DESTDIR)/usr/lib/x86_64-linux-gnu/rust/1.0/lib/rustlib/x86_64-unknown-linux-gnu/lib/".to_string());
+        r.add_rule("install -d $(DESTDIR)$(RUSTLIB_DIR)/".to_string());
         for lib in target_libs.into_iter() {
-            r.add_rule(format!("install -m 644 -s {} $(DESTDIR)/usr/lib/x86_64-linux-gnu/rust/1.0/lib/rustlib/x86_64-unknown-linux-gnu/lib/", lib));
+            r.add_rule(format!("install -m 644 -s {} $(DESTDIR)$(RUSTLIB_DIR)/",
+                               lib));
+        }
+        if !target_bins.is_empty() {
+            r.add_rule("install -d $(DESTDIR)/usr/bin/".to_string());
+        }
+        for bin in target_bins.into_iter() {
+            r.add_rule(format!("install -m 755 -s {} $(DESTDIR)/usr/bin/", bin));
         }
         mk_rules.push(r);
         
@@ -508,23 +1303,38 @@ pub fn debianize(manifest_path: &Path,
 
     let deb_makefile = deb_dir.join("Makefile.cargo");
     {
-        let mut f = match File::create(&deb_makefile) {
-            Ok(f) => f,
-            Err(e) => return Err(human(e))
+        // Unless a target is requested explicitly, build for the host
+        // architecture of the package build, so that the same Makefile works
+        // for every architecture and for cross builds. Architectures missing
+        // from the table fall back to the host of the rustc running cargo.
+        // The rustc version is the one of the toolchain the package is built
+        // with.
+        let arch_targets = DEB_ARCH_TRIPLES.iter().map(|&(arch, triple)| {
+            format!("RUST_TARGET_{} = {}", arch, triple)
+        }).collect::<Vec<_>>().connect("\n");
+        let rust_target = match options.target {
+            Some(target) => target.to_string(),
+            None => format!("$(or $(RUST_TARGET_$(DEB_HOST_ARCH)),{})",
+                            options.config.rustc_host()),
         };
 
         mk_rules.reverse();
         let rules = mk_rules.iter().map(|r| r.serialize())
             .collect::<Vec<String>>().connect("\n\n");
-        match f.write(format!("#!/usr/bin/make -f
+        files.push((deb_makefile, format!("#!/usr/bin/make -f
 
 # Automatically generated by cargo. DO NOT EDIT.
 
+DEB_HOST_MULTIARCH ?= $(shell dpkg-architecture -qDEB_HOST_MULTIARCH)
+DEB_HOST_ARCH ?= $(shell dpkg-architecture -qDEB_HOST_ARCH)
+
 {}
-", rules).as_bytes()) {
-            Ok(_) => {},
-            Err(e) => return Err(human(e))
-        };
+RUST_TARGET ?= {}
+RUST_VERSION ?= $(shell rustc -vV | sed -n 's/^release: \\([0-9]*\\.[0-9]*\\).*/\\1/p')
+RUSTLIB_DIR = /usr/lib/$(DEB_HOST_MULTIARCH)/rust/$(RUST_VERSION)/lib/rustlib/$(RUST_TARGET)/lib
+
+{}
+", arch_targets, rust_target, rules)));
     }
 
 
@@ -536,105 +1346,399 @@ pub fn debianize(manifest_path: &Path,
 
 
     
-    /*
-    let mut test_targets = cargo_targets.iter()
-        .filter(|target| target.profile().env() == "test");
-    let mut doc_targets = cargo_targets.iter()
-        .filter(|target| target.profile().env() == "doc");
-     */
-    for target in release_targets {
-        debug!("tgt name: {}, src path: {:?}, metadata: {:?}, profile: {:?}", target.name(),
-               target.src_path(), target.metadata(), target.profile());
+    let deb_compat = deb_dir.join("compat");
+    if !deb_compat.exists() {
+        files.push((deb_compat, "9\n".to_string()));
+    }
 
-        if target.is_lib() {
-            
-        } else if target.is_bin() {
-        } else if target.is_example() {
+    let deb_source_format = deb_dir.join("source").join("format");
+    if !deb_source_format.exists() {
+        files.push((deb_source_format, "3.0 (quilt)\n".to_string()));
+    }
+
+    let deb_rules = deb_dir.join("rules");
+    if !deb_rules.exists() {
+        files.push((deb_rules, "#!/usr/bin/make -f
+
+%:
+\tdh $@
+".to_string()));
+
+/* FIXME: mark executable
+        match fs::chmod(&deb_rules, old_io::USER_EXEC) {
+            Ok(_) => { },
+            Err(e) => return Err(human(e))
+        }
+*/
+    }
+
+    files.push((deb_control, cf.to_string()));
+
+    try!(emit_files(&files, cwd, options));
+
+    if options.recursive {
+        try!(debianize_dependencies(&package, options));
+    }
+    Ok(())
+}
+
+/// Resolves the dependency graph of the workspace `ws`, guided by its lockfile.
+/// Unlike `ops::resolve_ws` this never writes the lockfile, which debianizing,
+/// and especially a dry run, must not touch.
+fn resolve_without_writing(registry: &mut PackageRegistry, ws: &Workspace)
+                           -> CargoResult<Resolve> {
+    let previous = try!(ops::load_pkg_lockfile(ws.root()));
+    ops::resolve_with_previous(registry, ws, ws.members(), Method::Everything,
+                               ResolveOptions::default(), previous.as_ref(),
+                               None)
+}
+
+/// Returns the names of the lib targets of the direct dependencies of
+/// `package`, keyed by package name.
+fn dep_lib_names(package: &Package, config: &Config)
+                 -> CargoResult<HashMap<String, String>> {
+    let mut registry = PackageRegistry::new(config);
+    let ws = try!(Workspace::new(package.manifest_path(), config));
+    let resolve = try!(resolve_without_writing(&mut registry, &ws));
+    let ids: Vec<PackageId> = match resolve.deps(package.package_id()) {
+        Some(deps) => deps.cloned().collect(),
+        None => Vec::new(),
+    };
+    let packages = try!(registry.get(&ids).chain_error(|| {
+        human("unable to get packages from source")
+    }));
+    Ok(packages.iter().filter_map(|pkg| {
+        pkg.targets().iter().find(|t| t.is_lib()).map(|t| {
+            (pkg.name().to_string(), t.name().to_string())
+        })
+    }).collect())
+}
+
+/// Returns the names of all binary packages listed in a Packages file.
+fn read_known_packages(packages_file: &Path) -> CargoResult<HashSet<String>> {
+    let cf = match ControlFile::from_file(packages_file) {
+        Ok(f) => f,
+        Err(e) => return Err(human(
+            format!("Unable to read {}: {}", packages_file.display(), e)))
+    };
+    Ok(cf.get_paragraphs().iter().filter_map(|p| {
+        p.get_entry("Package").map(|s| s.to_string())
+    }).collect())
+}
+
+/// Copies the files of a downloaded package to `dst`, so it can be
+/// debianized without touching the shared source caches.
+fn copy_package(pkg: &Package, dst: &Path, options: &DebianizeOptions)
+                -> CargoResult<()> {
+    let src = try!(PathSource::for_path(pkg.root(), options.config));
+    let root = pkg.root();
+    for file in try!(src.list_files(pkg)).iter() {
+        let relative = file.relative_from(&root).unwrap();
+        let target = dst.join(relative);
+        try!(fs::create_dir_all(target.parent().unwrap()));
+        try!(fs::copy(file, &target).chain_error(|| {
+            human(format!("failed to copy `{}` to `{}`", file.display(),
+                          target.display()))
+        }));
+    }
+    Ok(())
+}
+
+/// Resolves the dependency graph of `package`, downloads every registry and
+/// git dependency and creates a debianized source tree for each one not
+/// already known to the archive.
+fn debianize_dependencies(package: &Package, options: &DebianizeOptions)
+                          -> CargoResult<()> {
+    let known = match options.packages_file {
+        Some(path) => try!(read_known_packages(path)),
+        None => HashSet::new(),
+    };
+
+    // A member of a workspace is resolved along with the whole workspace,
+    // whose lockfile is kept by the root.
+    let mut registry = PackageRegistry::new(options.config);
+    let ws = try!(Workspace::new(package.manifest_path(), options.config));
+    let resolve = try!(resolve_without_writing(&mut registry, &ws));
+
+    // Path dependencies are built along with the root package.
+    let ids: Vec<PackageId> = resolve.iter().filter(|id| {
+        *id != package.package_id() && !id.source_id().is_path()
+    }).cloned().collect();
+    let packages = try!(registry.get(&ids).chain_error(|| {
+        human("unable to get packages from source")
+    }));
+
+    let output_dir = match options.output_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => package.root().join("target").join("debian"),
+    };
+
+    let dep_options = DebianizeOptions {
+        recursive: false,
+        ..*options
+    };
+
+    let mut missing = Vec::new();
+    for pkg in packages.iter() {
+        let dpkg_name = xform_pkg_name(pkg.name());
+        if known.contains(&format!("{}-dev", dpkg_name)) {
+            debug!("{} is already packaged", pkg);
+            continue
+        }
+
+        let dst = output_dir.join(format!("{}-{}", dpkg_name, pkg.version()));
+        if options.dry_run || options.diff {
+            if !dst.exists() {
+                try!(options.config.shell().status("Missing", pkg));
+                missing.push(pkg.to_string());
+                continue
+            }
         } else {
-            unreachable!();
+            try!(options.config.shell().status("Debianizing", pkg));
+            if !dst.exists() {
+                try!(copy_package(pkg, &dst, options));
+            }
         }
-        
+        try!(debianize(&dst.join("Cargo.toml"), &dep_options));
+    }
 
-        // is_lib, is_dylib, is_rlib, is_staticlib, is_bin, is_example
+    // Like an out of date debian directory, a dependency which hasn't been
+    // debianized yet fails a dry run.
+    if options.dry_run && !missing.is_empty() {
+        return Err(human(format!("The debianized sources of these \
+                                  dependencies are missing:\n  {}",
+                                 missing.connect("\n  "))))
+    }
+    Ok(())
+}
 
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
 
+    use semver::{Version, VersionReq};
+    use debian::package::{Dependency, SingleDependency};
 
-/*
-        let cx = 0;
-        let req = 0;
-        rustc(package, target, cx, req);
-*/
+    use core::{dependency, PackageId, SourceId, Summary};
+    use sources::RegistrySource;
+    use util::ToUrl;
+
+    use super::{split_dpkg_version, bump_dpkg_revision, deb_upstream_version};
+    use super::{deb_version, deb_version_relations, features_closure};
+    use super::{deb_arch_for_triple, dep_architectures, deb_name};
+    use super::deb_archs_for_platform;
+    use super::DEB_ARCH_TRIPLES;
+
+    fn source_id() -> SourceId {
+        SourceId::for_registry(&RegistrySource::default_url().to_url().unwrap())
     }
 
+    fn dep(name: &str) -> dependency::Dependency {
+        dependency::Dependency::parse(name, Some("1.0"), &source_id()).unwrap()
+    }
 
-    let deb_compat = deb_dir.join("compat");
-    if !deb_compat.exists() {
-        let mut f = match File::create(&deb_compat) {
-            Ok(f) => f,
-            Err(e) => return Err(human(e))
-        };
-        match f.write("9\n".as_bytes()) {
-            Ok(_) => {},
-            Err(e) => return Err(human(e))
-        };
+    fn relations(req: &str) -> Vec<String> {
+        let req = VersionReq::parse(req).unwrap();
+        deb_version_relations(&req).unwrap().into_iter().map(|version| {
+            Dependency { alternatives: vec![SingleDependency {
+                package: "x".to_string(),
+                version: Some(version),
+                arch: None
+            }]}.to_string()
+        }).collect()
     }
 
+    #[test]
+    fn split_dpkg_versions() {
+        assert_eq!(split_dpkg_version("1.0.0"), (None, "1.0.0", None));
+        assert_eq!(split_dpkg_version("1.0.0-1"), (None, "1.0.0", Some("1")));
+        assert_eq!(split_dpkg_version("2:1.0.0-0ubuntu1"),
+                   (Some("2"), "1.0.0", Some("0ubuntu1")));
+        assert_eq!(split_dpkg_version("1.0.0~beta-2-1"),
+                   (None, "1.0.0~beta-2", Some("1")));
+    }
 
-    let deb_source_dir = deb_dir.join("source");
-    let deb_source_format = deb_source_dir.join("format");
-    if !deb_source_dir.exists() {
-        match fs::create_dir(&deb_source_dir) {
-            Ok(_) => {},
-            Err(e) => return Err(human(
-                format!("Unable to create the debian/source directory: {}", e)
-                    .to_string()))
-        }
-        debug!("Created the 'debian/source' directory - it didn't exist before.");
+    #[test]
+    fn bump_dpkg_revisions() {
+        assert_eq!(bump_dpkg_revision("1"), "2");
+        assert_eq!(bump_dpkg_revision("9"), "10");
+        assert_eq!(bump_dpkg_revision("0ubuntu1"), "0ubuntu2");
+        assert_eq!(bump_dpkg_revision("1+b"), "1+b1");
     }
 
-    if !deb_source_format.exists() {
-        let mut f = match File::create(&deb_source_format) {
-            Ok(f) => f,
-            Err(e) => return Err(human(e))
-        };
-        match f.write("3.0 (quilt)\n".as_bytes()) {
-            Ok(_) => {},
-            Err(e) => return Err(human(e))
-        };
+    #[test]
+    fn upstream_versions() {
+        let v = |s: &str| deb_upstream_version(&Version::parse(s).unwrap());
+        assert_eq!(v("1.0.0"), "1.0.0");
+        assert_eq!(v("1.0.0-beta"), "1.0.0~beta");
+        assert_eq!(v("1.0.0-beta.2"), "1.0.0~beta.2");
+        assert_eq!(v("1.0.0+build5"), "1.0.0");
     }
 
+    #[test]
+    fn deb_versions() {
+        assert_eq!(deb_version(1, None, None, None), "1.0.0");
+        assert_eq!(deb_version(1, Some(2), None, None), "1.2.0");
+        assert_eq!(deb_version(1, Some(2), Some(3), Some("rc.1")),
+                   "1.2.3~rc.1");
+    }
 
+    #[test]
+    fn caret_relations() {
+        assert_eq!(relations("1.2.3"), ["x (>= 1.2.3)", "x (<< 2.0.0~)"]);
+        assert_eq!(relations("^1.2"), ["x (>= 1.2.0)", "x (<< 2.0.0~)"]);
+        assert_eq!(relations("^0.2.3"), ["x (>= 0.2.3)", "x (<< 0.3.0~)"]);
+        assert_eq!(relations("^0.0.3"), ["x (>= 0.0.3)", "x (<< 0.0.4~)"]);
+    }
 
+    #[test]
+    fn tilde_relations() {
+        assert_eq!(relations("~1.2.3"), ["x (>= 1.2.3)", "x (<< 1.3.0~)"]);
+        assert_eq!(relations("~1.2"), ["x (>= 1.2.0)", "x (<< 1.3.0~)"]);
+        assert_eq!(relations("~1"), ["x (>= 1.0.0)", "x (<< 2.0.0~)"]);
+    }
 
-    let deb_rules = deb_dir.join("rules");
-    if !deb_rules.exists() {
-        {
-            let mut f = match File::create(&deb_rules) {
-                Ok(f) => f,
-                Err(e) => return Err(human(e))
-            };
-            match f.write("#!/usr/bin/make -f
+    #[test]
+    fn wildcard_relations() {
+        assert!(relations("*").is_empty());
+        assert_eq!(relations("1.*"), ["x (>= 1.0.0)", "x (<< 2.0.0~)"]);
+        assert_eq!(relations("1.2.*"), ["x (>= 1.2.0)", "x (<< 1.3.0~)"]);
+    }
 
-%:
-\tdh $@
-".as_bytes()) {
-                Ok(_) => {},
-                Err(e) => return Err(human(e))
-            };
-        }
+    #[test]
+    fn exact_and_range_relations() {
+        assert_eq!(relations("=1.2.3"), ["x (>= 1.2.3)", "x (<< 1.2.4~)"]);
+        assert_eq!(relations(">= 1.2.3, < 2.0.0"),
+                   ["x (>= 1.2.3)", "x (<< 2.0.0)"]);
+        assert_eq!(relations("> 1.2.3"), ["x (>= 1.2.4~)"]);
+        assert_eq!(relations("<= 1.2.3"), ["x (<< 1.2.4~)"]);
+    }
 
-/* FIXME: mark executable
-        match fs::chmod(&deb_rules, old_io::USER_EXEC) {
-            Ok(_) => { },
-            Err(e) => return Err(human(e))
+    #[test]
+    fn pre_release_relations() {
+        assert_eq!(relations("^1.0.0-beta"),
+                   ["x (>= 1.0.0~beta)", "x (<< 2.0.0~)"]);
+        assert_eq!(relations("=1.0.0-beta.2"),
+                   ["x (>= 1.0.0~beta.2)", "x (<< 1.0.1~)"]);
+    }
+
+    #[test]
+    fn deb_names() {
+        assert_eq!(deb_name("Foo_bar"), "foo-bar");
+        assert_eq!(deb_name("foo-bar2"), "foo-bar2");
+    }
+
+    #[test]
+    fn features_closures() {
+        let mut features = HashMap::new();
+        features.insert("default".to_string(), vec!["a".to_string()]);
+        features.insert("a".to_string(), vec!["b".to_string(),
+                                              "bar/baz".to_string()]);
+        features.insert("b".to_string(), vec!["foo".to_string()]);
+        features.insert("c".to_string(), vec![]);
+        let pkg_id = PackageId::new("pkg", "1.0.0", &source_id()).unwrap();
+        let summary = Summary::new(pkg_id, vec![
+            dep("foo").set_optional(true),
+            dep("bar").set_optional(true),
+        ], features).unwrap();
+
+        let closure = |names: &[&str]| {
+            let names = names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            let mut ret = features_closure(&summary, &names).into_iter()
+                                                            .collect::<Vec<_>>();
+            ret.sort();
+            ret
+        };
+        assert_eq!(closure(&["default"]), ["a", "b", "bar", "default", "foo"]);
+        assert_eq!(closure(&["b", "c"]), ["b", "c", "foo"]);
+        assert_eq!(closure(&["bar/baz"]), ["bar"]);
+        assert!(closure(&[]).is_empty());
+    }
+
+    #[test]
+    fn deb_archs_for_triples() {
+        let arch = |t: &str| deb_arch_for_triple(t);
+        assert_eq!(arch("x86_64-unknown-linux-gnu"), Some("amd64".to_string()));
+        assert_eq!(arch("i686-unknown-linux-gnu"), Some("i386".to_string()));
+        assert_eq!(arch("arm-unknown-linux-gnueabihf"),
+                   Some("armhf".to_string()));
+        assert_eq!(arch("arm-unknown-linux-gnueabi"), Some("armel".to_string()));
+        assert_eq!(arch("aarch64-unknown-linux-gnu"), Some("arm64".to_string()));
+        assert_eq!(arch("sparc64-unknown-linux-gnu"),
+                   Some("linux-any".to_string()));
+        assert_eq!(arch("x86_64-unknown-freebsd"), None);
+        assert_eq!(arch("x86_64-apple-darwin"), None);
+        assert_eq!(arch("i686-pc-windows-gnu"), None);
+    }
+
+    #[test]
+    fn deb_arch_triples_round_trip() {
+        for &(arch, triple) in DEB_ARCH_TRIPLES.iter() {
+            assert_eq!(deb_arch_for_triple(triple), Some(arch.to_string()));
         }
-*/
+        assert_eq!(deb_arch_for_triple("i586-unknown-linux-gnu"),
+                   Some("i386".to_string()));
+        assert_eq!(deb_arch_for_triple("armv7-unknown-linux-gnueabihf"),
+                   Some("armhf".to_string()));
     }
-    
 
+    #[test]
+    fn dep_architecture_restrictions() {
+        let on = |name: &str, platform: &str| {
+            dep(name).set_only_for_platform(Some(platform.to_string()))
+        };
+        let archs = dep_architectures(&[
+            dep("everywhere"),
+            on("linux", "x86_64-unknown-linux-gnu"),
+            on("linux", "i686-unknown-linux-gnu"),
+            on("mixed", "x86_64-unknown-linux-gnu"),
+            dep("mixed"),
+            on("windows", "i686-pc-windows-gnu"),
+        ]);
+        let arch = |name: &str| archs.get(name).unwrap().clone();
+        assert_eq!(arch("everywhere"), None);
+        assert_eq!(arch("linux"), Some(vec!["amd64".to_string(),
+                                            "i386".to_string()]));
+        assert_eq!(arch("mixed"), None);
+        assert_eq!(arch("windows"), Some(vec![]));
+    }
 
-    return match cf.serialize(&deb_control) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(human(format!("Error writing control file: {}", e)))
-    };
+    #[test]
+    fn cfg_architecture_restrictions() {
+        let archs = |platform: &str| deb_archs_for_platform(platform);
+        let list = |archs: &[&str]| {
+            Some(archs.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+        };
+        assert_eq!(archs("cfg(target_arch = \"x86_64\")"), list(&["amd64"]));
+        assert_eq!(archs("cfg(target_arch = \"arm\")"),
+                   list(&["armhf", "armel"]));
+        assert_eq!(archs("cfg(not(target_arch = \"x86\"))"), list(&["!i386"]));
+        assert_eq!(archs("cfg(not(target_arch = \"arm\"))"),
+                   list(&["!armhf", "!armel"]));
+        assert_eq!(archs("cfg(not(target_arch = \"sparc\"))"), None);
+        assert_eq!(archs("cfg(windows)"), Some(vec![]));
+        assert_eq!(archs("cfg(not(windows))"), None);
+        assert_eq!(archs("cfg(unix)"), None);
+        assert_eq!(archs("cfg(target_os = \"macos\")"), Some(vec![]));
+        assert_eq!(archs("x86_64-pc-windows-gnu"), Some(vec![]));
+    }
+
+    #[test]
+    fn negated_architecture_restrictions() {
+        let on = |name: &str, platform: &str| {
+            dep(name).set_only_for_platform(Some(platform.to_string()))
+        };
+        let archs = dep_architectures(&[
+            on("not-arm", "cfg(not(target_arch = \"arm\"))"),
+            on("not-arm-or-x86", "cfg(not(target_arch = \"arm\"))"),
+            on("not-arm-or-x86", "cfg(not(target_arch = \"x86\"))"),
+            on("not-armel", "cfg(not(target_arch = \"arm\"))"),
+            on("not-armel", "arm-unknown-linux-gnueabihf"),
+        ]);
+        let arch = |name: &str| archs.get(name).unwrap().clone();
+        assert_eq!(arch("not-arm"), Some(vec!["!armhf".to_string(),
+                                              "!armel".to_string()]));
+        assert_eq!(arch("not-arm-or-x86"), None);
+        assert_eq!(arch("not-armel"), Some(vec!["!armel".to_string()]));
+    }
 }
@@ -3,10 +3,8 @@ use std::fs;
 use std::path::Path;
 use std::process::Command;
 
-use core::PackageIdSpec;
-use core::source::Source;
+use core::{PackageIdSpec, Workspace};
 use ops;
-use sources::PathSource;
 use util::{CargoResult, human};
 
 pub struct DocOptions<'a, 'b: 'a> {
@@ -17,14 +15,12 @@ pub struct DocOptions<'a, 'b: 'a> {
 
 pub fn doc(manifest_path: &Path,
            options: &DocOptions) -> CargoResult<()> {
-    let mut source = try!(PathSource::for_path(manifest_path.parent().unwrap(),
-                                               options.compile_opts.config));
-    try!(source.update());
-    let package = try!(source.root_package());
+    let ws = try!(Workspace::new(manifest_path, options.compile_opts.config));
+    let package = ws.current();
 
     let mut lib_names = HashSet::new();
     let mut bin_names = HashSet::new();
-    if options.compile_opts.spec.is_none() {
+    if options.compile_opts.spec.is_empty() {
         for target in package.targets().iter().filter(|t| t.profile().is_doc()) {
             if target.is_lib() {
                 assert!(lib_names.insert(target.name()));
@@ -42,10 +38,13 @@ pub fn doc(manifest_path: &Path,
         }
     }
 
-    try!(ops::compile(manifest_path, &options.compile_opts));
+    for key in package.manifest().warnings().iter() {
+        try!(options.compile_opts.config.shell().warn(key))
+    }
+    try!(ops::compile_ws(&ws, &options.compile_opts));
 
     if options.open_result {
-        let name = match options.compile_opts.spec {
+        let name = match options.compile_opts.spec.get(0) {
             Some(spec) => try!(PackageIdSpec::parse(spec)).name().to_string(),
             None => {
                 match lib_names.iter().nth(0) {
@@ -3,10 +3,9 @@ use std::path::Path;
 
 use core::PackageId;
 use core::registry::PackageRegistry;
-use core::{Source, Resolve};
-use core::resolver::Method;
+use core::{Resolve, Workspace};
+use core::resolver::{Method, ResolveOptions};
 use ops;
-use sources::{PathSource};
 use util::config::{Config};
 use util::{CargoResult, human};
 
@@ -15,30 +14,29 @@ pub struct UpdateOptions<'a, 'b: 'a> {
     pub to_update: Option<&'a str>,
     pub precise: Option<&'a str>,
     pub aggressive: bool,
+    pub minimal_versions: bool,
 }
 
-pub fn generate_lockfile(manifest_path: &Path, config: &Config)
-                         -> CargoResult<()> {
-    let mut source = try!(PathSource::for_path(manifest_path.parent().unwrap(),
-                                               config));
-    try!(source.update());
-    let package = try!(source.root_package());
+pub fn generate_lockfile(manifest_path: &Path, config: &Config,
+                         minimal_versions: bool) -> CargoResult<()> {
+    let ws = try!(Workspace::new(manifest_path, config));
     let mut registry = PackageRegistry::new(config);
-    let resolve = try!(ops::resolve_with_previous(&mut registry, &package,
+    let resolve = try!(ops::resolve_with_previous(&mut registry, &ws,
+                                                  ws.members(),
                                                   Method::Everything,
+                                                  ResolveOptions {
+                                                      minimal_versions:
+                                                          minimal_versions,
+                                                  },
                                                   None, None));
-    try!(ops::write_pkg_lockfile(&package, &resolve));
+    try!(ops::write_pkg_lockfile(ws.root(), &resolve, config));
     Ok(())
 }
 
 pub fn update_lockfile(manifest_path: &Path,
                        opts: &UpdateOptions) -> CargoResult<()> {
-    let mut source = try!(PathSource::for_path(manifest_path.parent().unwrap(),
-                                               opts.config));
-    try!(source.update());
-    let package = try!(source.root_package());
-
-    let previous_resolve = match try!(ops::load_pkg_lockfile(&package)) {
+    let ws = try!(Workspace::new(manifest_path, opts.config));
+    let previous_resolve = match try!(ops::load_pkg_lockfile(ws.root())) {
         Some(resolve) => resolve,
         None => return Err(human("A Cargo.lock must exist before it is updated"))
     };
@@ -81,11 +79,16 @@ pub fn update_lockfile(manifest_path: &Path,
     }
 
     let resolve = try!(ops::resolve_with_previous(&mut registry,
-                                                  &package,
+                                                  &ws,
+                                                  ws.members(),
                                                   Method::Everything,
+                                                  ResolveOptions {
+                                                      minimal_versions:
+                                                          opts.minimal_versions,
+                                                  },
                                                   Some(&previous_resolve),
                                                   Some(&to_avoid)));
-    try!(ops::write_pkg_lockfile(&package, &resolve));
+    try!(ops::write_pkg_lockfile(ws.root(), &resolve, opts.config));
     return Ok(());
 
     fn fill_with_deps<'a>(resolve: &'a Resolve, dep: &'a PackageId,
@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::slice;
+
+use core::dependency::Kind;
+use core::registry::PackageRegistry;
+use core::resolver::{Method, ResolveOptions};
+use core::{Package, PackageId, SourceId, Dependency, Target, Workspace};
+use ops;
+use util::{CargoResult, Config, human, ChainError};
+
+/// The version of the format written by `cargo metadata`. It is bumped on
+/// every change which could break existing consumers, i.e. anything but the
+/// addition of new fields.
+pub const METADATA_VERSION: u32 = 1;
+
+pub struct OutputMetadataOptions<'a, 'b: 'a> {
+    pub config: &'a Config<'b>,
+    pub features: &'a [String],
+    pub no_default_features: bool,
+    pub no_deps: bool,
+    pub version: u32,
+}
+
+/// The document printed by `cargo metadata`, see `src/doc/metadata.md` for a
+/// description of the format.
+#[derive(RustcEncodable)]
+pub struct ExportInfo {
+    version: u32,
+    packages: Vec<SerializedPackage>,
+    resolve: Option<SerializedResolve>,
+}
+
+#[derive(RustcEncodable)]
+struct SerializedPackage {
+    id: String,
+    name: String,
+    version: String,
+    source: String,
+    manifest_path: String,
+    dependencies: Vec<SerializedDependency>,
+    targets: Vec<SerializedTarget>,
+    features: BTreeMap<String, Vec<String>>,
+    links: Option<String>,
+    authors: Vec<String>,
+    keywords: Vec<String>,
+    license: Option<String>,
+    license_file: Option<String>,
+    description: Option<String>,
+    readme: Option<String>,
+    homepage: Option<String>,
+    repository: Option<String>,
+    documentation: Option<String>,
+}
+
+#[derive(RustcEncodable)]
+struct SerializedDependency {
+    name: String,
+    source: String,
+    req: String,
+    kind: &'static str,
+    optional: bool,
+    uses_default_features: bool,
+    features: Vec<String>,
+    target: Option<String>,
+}
+
+#[derive(RustcEncodable)]
+struct SerializedTarget {
+    name: String,
+    kind: Vec<&'static str>,
+    src_path: String,
+}
+
+#[derive(RustcEncodable)]
+struct SerializedResolve {
+    root: String,
+    nodes: Vec<SerializedNode>,
+}
+
+#[derive(RustcEncodable)]
+struct SerializedNode {
+    id: String,
+    dependencies: Vec<String>,
+    features: Vec<String>,
+}
+
+/// Describes the package at `manifest_path` and, unless `no_deps` is given,
+/// all the packages of its resolved dependency graph.
+pub fn output_metadata(manifest_path: &Path,
+                       options: &OutputMetadataOptions)
+                       -> CargoResult<ExportInfo> {
+    if options.version != METADATA_VERSION {
+        return Err(human(format!("metadata version {} not supported, only {} \
+                                  is currently supported", options.version,
+                                 METADATA_VERSION)))
+    }
+
+    let ws = try!(Workspace::new(manifest_path, options.config));
+    let package = ws.current();
+
+    if options.no_deps {
+        return Ok(ExportInfo {
+            version: METADATA_VERSION,
+            packages: vec![serialize_package(package)],
+            resolve: None,
+        })
+    }
+
+    let features = options.features.iter().flat_map(|s| {
+        s.split(' ')
+    }).map(|s| s.to_string()).collect::<Vec<String>>();
+
+    // The lockfile guides the resolution like for a build, but isn't updated
+    // since only the requested features are resolved.
+    let previous = try!(ops::load_pkg_lockfile(ws.root()));
+    let mut registry = PackageRegistry::new(options.config);
+    let method = Method::Required {
+        dev_deps: true,
+        features: &features,
+        uses_default_features: !options.no_default_features,
+        target_platform: None,
+    };
+    let resolve = try!(ops::resolve_with_previous(&mut registry, &ws,
+                                                  slice::ref_slice(package),
+                                                  method,
+                                                  ResolveOptions::default(),
+                                                  previous.as_ref(), None));
+    let ids = resolve.iter().cloned().collect::<Vec<PackageId>>();
+    let packages = try!(registry.get(&ids).chain_error(|| {
+        human("unable to get packages from source")
+    }));
+    let mut packages = packages.iter().map(serialize_package)
+                               .collect::<Vec<_>>();
+    packages.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut nodes = ids.iter().map(|id| {
+        let mut dependencies = resolve.deps(id).into_iter().flat_map(|i| i)
+                                      .collect::<Vec<_>>();
+        dependencies.sort();
+        let mut features = resolve.features(id).map(|f| {
+            f.iter().cloned().collect::<Vec<_>>()
+        }).unwrap_or(Vec::new());
+        features.sort();
+        SerializedNode {
+            id: encode_id(id),
+            dependencies: dependencies.into_iter().map(encode_id).collect(),
+            features: features,
+        }
+    }).collect::<Vec<_>>();
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(ExportInfo {
+        version: METADATA_VERSION,
+        packages: packages,
+        resolve: Some(SerializedResolve {
+            root: encode_id(resolve.root()),
+            nodes: nodes,
+        }),
+    })
+}
+
+// Package ids are written the same way as in `Cargo.lock`.
+fn encode_id(id: &PackageId) -> String {
+    format!("{} {} ({})", id.name(), id.version(), encode_source(id.source_id()))
+}
+
+// Path sources never appear in `Cargo.lock`, they are written with a `path+`
+// prefix like the other kinds of sources.
+fn encode_source(source_id: &SourceId) -> String {
+    if source_id.is_path() {
+        format!("path+{}", source_id.url())
+    } else {
+        source_id.to_url()
+    }
+}
+
+fn serialize_package(package: &Package) -> SerializedPackage {
+    let manifest = package.manifest();
+    let summary = package.summary();
+    let metadata = manifest.metadata();
+    SerializedPackage {
+        id: encode_id(package.package_id()),
+        name: package.name().to_string(),
+        version: package.version().to_string(),
+        source: encode_source(package.package_id().source_id()),
+        manifest_path: package.manifest_path().display().to_string(),
+        dependencies: package.dependencies().iter()
+                             .map(serialize_dependency).collect(),
+        targets: serialize_targets(manifest.targets()),
+        features: summary.features().iter().map(|(k, v)| {
+            (k.clone(), v.clone())
+        }).collect(),
+        links: manifest.links().map(|s| s.to_string()),
+        authors: metadata.authors.clone(),
+        keywords: metadata.keywords.clone(),
+        license: metadata.license.clone(),
+        license_file: metadata.license_file.clone(),
+        description: metadata.description.clone(),
+        readme: metadata.readme.clone(),
+        homepage: metadata.homepage.clone(),
+        repository: metadata.repository.clone(),
+        documentation: metadata.documentation.clone(),
+    }
+}
+
+fn serialize_dependency(dep: &Dependency) -> SerializedDependency {
+    SerializedDependency {
+        name: dep.name().to_string(),
+        source: encode_source(dep.source_id()),
+        req: dep.version_req().to_string(),
+        kind: match dep.kind() {
+            Kind::Normal => "normal",
+            Kind::Development => "dev",
+            Kind::Build => "build",
+        },
+        optional: dep.is_optional(),
+        uses_default_features: dep.uses_default_features(),
+        features: dep.features().to_vec(),
+        target: dep.only_for_platform().map(|s| s.to_string()),
+    }
+}
+
+// A manifest has a `Target` per profile it's built with, only one entry is
+// listed for each of them. Tests and benchmarks are binaries which are never
+// built with the `compile` or `release` profiles.
+fn serialize_targets(targets: &[Target]) -> Vec<SerializedTarget> {
+    let mut ret: Vec<SerializedTarget> = Vec::new();
+    for target in targets.iter() {
+        let src_path = target.src_path().display().to_string();
+        if ret.iter().any(|t| t.name == target.name() && t.src_path == src_path) {
+            continue
+        }
+        let kind = if target.profile().is_custom_build() {
+            vec!["custom-build"]
+        } else if target.is_example() {
+            vec!["example"]
+        } else if target.is_bin() {
+            let envs = targets.iter().filter(|t| {
+                t.name() == target.name() && t.src_path() == target.src_path()
+            }).map(|t| t.profile().env()).collect::<Vec<_>>();
+            if envs.iter().any(|&e| e == "compile" || e == "release") {
+                vec!["bin"]
+            } else if envs.iter().any(|&e| e == "test") {
+                vec!["test"]
+            } else {
+                vec!["bench"]
+            }
+        } else {
+            target.rustc_crate_types()
+        };
+        ret.push(SerializedTarget {
+            name: target.name().to_string(),
+            kind: kind,
+            src_path: src_path,
+        });
+    }
+    ret
+}
@@ -6,7 +6,7 @@ use tar::Archive;
 use flate2::{GzBuilder, Compression};
 use flate2::read::GzDecoder;
 
-use core::{Source, SourceId, Package, PackageId};
+use core::{Source, SourceId, Package, PackageId, Workspace};
 use sources::PathSource;
 use util::{self, CargoResult, human, internal, ChainError, Config};
 use ops;
@@ -178,7 +178,7 @@ fn run_verify(config: &Config, pkg: &Package, tar: &Path)
     let new_pkg = Package::new(new_manifest, &manifest_path, &new_src);
 
     // Now that we've rewritten all our path dependencies, compile it!
-    try!(ops::compile_pkg(&new_pkg, &ops::CompileOptions {
+    try!(ops::compile_ws(&Workspace::single(new_pkg), &ops::CompileOptions {
         env: "compile",
         config: config,
         jobs: None,
@@ -186,8 +186,12 @@ fn run_verify(config: &Config, pkg: &Package, tar: &Path)
         dev_deps: false,
         features: &[],
         no_default_features: false,
-        spec: None,
+        spec: &[],
+        all_members: false,
         lib_only: false,
+        bin_only: None,
+        target_rustc_args: None,
+        profile: None,
         exec_engine: None,
     }));
 
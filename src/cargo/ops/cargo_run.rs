@@ -2,9 +2,8 @@ use std::path::Path;
 
 use ops::{self, ExecEngine};
 use util::{CargoResult, human, process, ProcessError, ChainError};
+use core::Workspace;
 use core::manifest::TargetKind;
-use core::source::Source;
-use sources::PathSource;
 
 pub fn run(manifest_path: &Path,
            target_kind: TargetKind,
@@ -12,11 +11,9 @@ pub fn run(manifest_path: &Path,
            options: &ops::CompileOptions,
            args: &[String]) -> CargoResult<Option<ProcessError>> {
     let config = options.config;
-    let mut src = try!(PathSource::for_path(&manifest_path.parent().unwrap(),
-                                            config));
-    try!(src.update());
-    let root = try!(src.root_package());
-    let env = options.env;
+    let ws = try!(Workspace::new(manifest_path, config));
+    let root = ws.current();
+    let env = try!(ops::target_env(&ws, options));
     let mut bins = root.manifest().targets().iter().filter(|a| {
         let matches_kind = match target_kind {
             TargetKind::Bin => a.is_bin(),
@@ -24,7 +21,7 @@ pub fn run(manifest_path: &Path,
             TargetKind::Lib(_) => false,
         };
         let matches_name = name.as_ref().map_or(true, |n| *n == a.name());
-        matches_kind && matches_name && a.profile().env() == env &&
+        matches_kind && matches_name && a.profile().env() == &env[..] &&
             !a.profile().is_custom_build()
     });
     let bin = try!(bins.next().chain_error(|| {
@@ -46,13 +43,17 @@ pub fn run(manifest_path: &Path,
         None => {}
     }
 
-    let compile = try!(ops::compile(manifest_path, options));
-    let dst = manifest_path.parent().unwrap().join("target");
+    for key in root.manifest().warnings().iter() {
+        try!(config.shell().warn(key))
+    }
+    let compile = try!(ops::compile_ws(&ws, options)).pop().unwrap();
+    let dst = root.absolute_target_dir();
     let dst = match options.target {
         Some(target) => dst.join(target),
         None => dst,
     };
-    let exe = match (bin.profile().dest(), bin.is_example()) {
+    let dest = options.profile.unwrap_or(bin.profile().dest());
+    let exe = match (dest, bin.is_example()) {
         (s, true) => dst.join(s).join("examples").join(bin.name()),
         (s, false) => dst.join(s).join(bin.name()),
     };
@@ -60,7 +61,7 @@ pub fn run(manifest_path: &Path,
         Some(path) => path,
         None => &*exe,
     };
-    let mut process = try!(compile.target_process(exe, &root))
+    let mut process = try!(compile.target_process(exe, root))
                                   .into_process_builder();
     process.args(args).cwd(config.cwd());
 
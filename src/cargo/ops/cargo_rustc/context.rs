@@ -371,6 +371,23 @@ impl<'a, 'b: 'a> Context<'a, 'b> {
         }
     }
 
+    /// Extra flags to pass to rustc when compiling `target` of `pkg`.
+    ///
+    /// These are the configured rustflags for `kind`, followed by the
+    /// arguments given to `cargo rustc` if `target` is the one they were
+    /// given for.
+    pub fn rustflags_args(&self, pkg: &Package, target: &Target,
+                          kind: Kind) -> Vec<String> {
+        let mut args = self.target_config(kind).rustflags.clone();
+        if let Some((ref selected, ref extra)) = self.build_config.target_rustc_args {
+            if pkg.package_id() == self.compilation.package.package_id() &&
+               target == selected {
+                args.extend(extra.iter().cloned());
+            }
+        }
+        args
+    }
+
     /// Number of jobs specified for this build
     pub fn jobs(&self) -> u32 { self.build_config.jobs }
 
@@ -383,7 +400,11 @@ impl<'a, 'b: 'a> Context<'a, 'b> {
     ///
     /// This may involve overriding some options such as debug information,
     /// rpath, opt level, etc.
-    pub fn profile(&self, target: &Target) -> Profile {
+    ///
+    /// The settings of the profile of the same environment in the root package
+    /// replace those of the target's own profile. The overrides for `pkg` and
+    /// the custom profile being built with are then applied on top of it.
+    pub fn profile(&self, pkg: &Package, target: &Target) -> Profile {
         let mut profile = target.profile().clone();
         let root_package = self.get_package(self.resolve.root());
         for target in root_package.manifest().targets().iter() {
@@ -393,6 +414,23 @@ impl<'a, 'b: 'a> Context<'a, 'b> {
                              .set_debug(root_profile.debug())
                              .set_rpath(root_profile.rpath())
         }
+        if profile.is_custom_build() { return profile }
+
+        let profiles = &self.build_config.profiles;
+        let env = profile.env().to_string();
+        if let Some(o) = profiles.package_override(&env, pkg.name()) {
+            profile = o.apply(profile);
+        }
+        let custom = self.build_config.profile.as_ref().and_then(|name| {
+            profiles.custom(name).map(|custom| (name, custom))
+        });
+        if let Some((name, custom)) = custom {
+            if custom.env != env { return profile }
+            profile = custom.settings.apply(profile).set_dest(name);
+            if let Some(o) = profiles.package_override(name, pkg.name()) {
+                profile = o.apply(profile);
+            }
+        }
         profile
     }
 }
@@ -1,12 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs::{self, File};
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str;
 use std::sync::Mutex;
 
 use core::{Package, Target, PackageId, PackageSet};
-use util::{CargoResult, human, Human};
+use util::{CargoResult, Config, human, Human};
 use util::{internal, ChainError};
 
 use super::job::Work;
@@ -21,8 +21,18 @@ pub struct BuildOutput {
     pub library_paths: Vec<PathBuf>,
     /// Names and link kinds of libraries, suitable for the `-l` flag
     pub library_links: Vec<String>,
+    /// Names of `cfg`s to pass to rustc when compiling the package
+    pub cfgs: Vec<String>,
+    /// Environment variables to set when compiling the package
+    pub env: Vec<(String, String)>,
     /// Metadata to pass to the immediate dependencies
     pub metadata: Vec<(String, String)>,
+    /// Warnings to show to the user
+    pub warnings: Vec<String>,
+    /// Paths, relative to the package root, whose changes rerun the script
+    pub rerun_if_changed: Vec<PathBuf>,
+    /// Environment variables whose changes rerun the script
+    pub rerun_if_env_changed: Vec<String>,
 }
 
 pub type BuildMap = HashMap<(PackageId, Kind), BuildOutput>;
@@ -56,7 +66,7 @@ pub fn prepare(pkg: &Package, target: &Target, req: Platform,
     let profile_target = pkg.targets().iter().find(|t| {
         cx.is_relevant_target(t) && !t.profile().is_custom_build()
     }).unwrap_or(target);
-    let profile = cx.profile(profile_target);
+    let profile = cx.profile(pkg, profile_target);
     let to_exec = to_exec.into_os_string();
     let mut p = try!(super::process(CommandType::Host(to_exec), pkg, target, cx));
     p.env("OUT_DIR", &build_output)
@@ -179,7 +189,7 @@ pub fn prepare(pkg: &Package, target: &Target, req: Platform,
     //
     // Note that the freshness calculation here is the build_cmd freshness, not
     // target specific freshness. This is because we don't actually know what
-    // the inputs are to this command!
+    // the inputs are to this command, unless its last run listed them!
     //
     // Also note that a fresh build command needs to
     let (freshness, dirty, fresh) =
@@ -233,6 +243,28 @@ impl BuildState {
         BuildState { outputs: Mutex::new(outputs) }
     }
 
+    /// Shows the warnings printed by the build script of `id` once it has run.
+    /// Only the build scripts of path packages get to warn the user, and a
+    /// script run for both the host and the target warns once.
+    pub fn emit_warnings(&self, id: &PackageId, config: &Config)
+                         -> CargoResult<()> {
+        if !id.source_id().is_path() { return Ok(()) }
+        let mut warnings = BTreeSet::new();
+        {
+            let outputs = self.outputs.lock().unwrap();
+            for kind in [Kind::Host, Kind::Target].iter() {
+                if let Some(output) = outputs.get(&(id.clone(), *kind)) {
+                    warnings.extend(output.warnings.iter().cloned());
+                }
+            }
+        }
+        for warning in warnings.into_iter() {
+            try!(config.shell().warn(format!("warning: {} (build script of \
+                                              `{}`)", warning, id)));
+        }
+        Ok(())
+    }
+
     fn insert(&self, id: PackageId, req: Platform,
               output: BuildOutput) {
         let mut outputs = self.outputs.lock().unwrap();
@@ -250,13 +282,33 @@ impl BuildState {
     }
 }
 
+/// Returns the directory of a library search path as given to `-L`, without
+/// the `KIND=` prefix it may start with.
+pub fn link_search_dir(path: &Path) -> PathBuf {
+    let mut parts = match path.to_str() {
+        Some(s) => s.splitn(1, '='),
+        None => return path.to_path_buf(),
+    };
+    match (parts.next().unwrap(), parts.next()) {
+        ("dependency", Some(dir)) | ("crate", Some(dir)) |
+        ("native", Some(dir)) | ("framework", Some(dir)) |
+        ("all", Some(dir)) => PathBuf::new(dir),
+        _ => path.to_path_buf(),
+    }
+}
+
 impl BuildOutput {
     // Parses the output of a script.
     // The `pkg_name` is used for error messages.
     pub fn parse(input: &str, pkg_name: &str) -> CargoResult<BuildOutput> {
         let mut library_paths = Vec::new();
         let mut library_links = Vec::new();
+        let mut cfgs = Vec::new();
+        let mut env = Vec::new();
         let mut metadata = Vec::new();
+        let mut warnings = Vec::new();
+        let mut rerun_if_changed = Vec::new();
+        let mut rerun_if_env_changed = Vec::new();
         let whence = format!("build script of `{}`", pkg_name);
 
         for line in input.lines() {
@@ -287,6 +339,20 @@ impl BuildOutput {
                 );
                 library_links.extend(links.into_iter());
                 library_paths.extend(libs.into_iter());
+            } else if key == "rustc-link-lib" {
+                library_links.push(value.to_string());
+            } else if key == "rustc-link-search" {
+                library_paths.push(PathBuf::new(value));
+            } else if key == "rustc-cfg" {
+                cfgs.push(value.to_string());
+            } else if key == "rustc-env" {
+                env.push(try!(BuildOutput::parse_rustc_env(value, &whence)));
+            } else if key == "warning" {
+                warnings.push(value.to_string());
+            } else if key == "rerun-if-changed" {
+                rerun_if_changed.push(PathBuf::new(value));
+            } else if key == "rerun-if-env-changed" {
+                rerun_if_env_changed.push(value.to_string());
             } else {
                 metadata.push((key.to_string(), value.to_string()))
             }
@@ -295,7 +361,12 @@ impl BuildOutput {
         Ok(BuildOutput {
             library_paths: library_paths,
             library_links: library_links,
+            cfgs: cfgs,
+            env: env,
             metadata: metadata,
+            warnings: warnings,
+            rerun_if_changed: rerun_if_changed,
+            rerun_if_env_changed: rerun_if_env_changed,
         })
     }
 
@@ -331,4 +402,16 @@ impl BuildOutput {
         }
         Ok((library_paths, library_links))
     }
+
+    pub fn parse_rustc_env(value: &str, whence: &str)
+                           -> CargoResult<(String, String)> {
+        let mut iter = value.splitn(1, |c| c == '=');
+        match (iter.next(), iter.next()) {
+            (Some(name), Some(val)) if !name.is_empty() => {
+                Ok((name.to_string(), val.to_string()))
+            }
+            _ => Err(human(format!("Variable in rustc-env has no value \
+                                    in {}: `{}`", whence, value))),
+        }
+    }
 }
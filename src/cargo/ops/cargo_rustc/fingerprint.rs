@@ -1,4 +1,5 @@
 use std::collections::hash_map::Entry::{Occupied, Vacant};
+use std::env;
 use std::fs::{self, File, OpenOptions};
 use std::io::prelude::*;
 use std::io::{BufReader, SeekFrom};
@@ -8,7 +9,7 @@ use core::{Package, Target};
 use util;
 use util::{CargoResult, Fresh, Dirty, Freshness, internal, profile, ChainError};
 
-use super::Kind;
+use super::{Kind, BuildOutput};
 use super::job::Work;
 use super::context::Context;
 
@@ -110,6 +111,14 @@ pub struct Fingerprint {
 enum LocalFingerprint {
     Precalculated(String),
     MtimeBased(Option<u64>, PathBuf),
+    // The inputs of a build script, as listed in the output of its last run,
+    // falling back to the fingerprint of its whole package.
+    BuildScript {
+        pkg_name: String,
+        root: PathBuf,
+        output: PathBuf,
+        pkg_fingerprint: String,
+    },
 }
 
 impl Fingerprint {
@@ -125,6 +134,11 @@ impl Fingerprint {
                 debug!("resolving: {}", p.display());
                 try!(fs::metadata(p)).modified().to_string()
             }
+            LocalFingerprint::BuildScript { ref pkg_name, ref root, ref output,
+                                            ref pkg_fingerprint } => {
+                try!(build_script_fingerprint(pkg_name, root, output,
+                                              pkg_fingerprint))
+            }
         };
         debug!("inputs: {} {} {:?}", known, self.extra, deps);
         Ok(util::short_hash(&(known, &self.extra, &deps)))
@@ -156,7 +170,7 @@ fn calculate<'a, 'b>(cx: &mut Context<'a, 'b>,
 
     // First, calculate all statically known "salt data" such as the profile
     // information (compiler flags), the compiler version, activated features,
-    // and target configuration.
+    // extra rustc flags and target configuration.
     let features = cx.resolve.features(pkg.package_id());
     let features = features.map(|s| {
         let mut v = s.iter().collect::<Vec<&String>>();
@@ -164,7 +178,8 @@ fn calculate<'a, 'b>(cx: &mut Context<'a, 'b>,
         v
     });
     let extra = util::short_hash(&(cx.config.rustc_version(), target, &features,
-                                   cx.profile(target)));
+                                   cx.profile(pkg, target),
+                                   cx.rustflags_args(pkg, target, kind)));
 
     // Next, recursively calculate the fingerprint for all of our dependencies.
     let deps = try!(cx.dep_targets(pkg, target).into_iter().map(|(p, t)| {
@@ -225,8 +240,11 @@ fn use_dep_info(pkg: &Package, target: &Target) -> bool {
 /// 3. A build command must itself provide a dep-info-like file stating how it
 ///    should be considered dirty or not.
 ///
-/// The currently implemented solution is option (1), although it is planned to
-/// migrate to option (2) in the near future.
+/// Option (1) is the default. A build command may opt into option (3) by
+/// printing `cargo:rerun-if-changed=PATH` or `cargo:rerun-if-env-changed=VAR`
+/// lines, which are read back from the output saved by its last run. It is
+/// then only dirty when one of the listed paths or environment variables
+/// changes.
 pub fn prepare_build_cmd(cx: &mut Context, pkg: &Package, kind: Kind)
                          -> CargoResult<Preparation> {
     let _p = profile::start(format!("fingerprint build cmd: {}",
@@ -236,11 +254,18 @@ pub fn prepare_build_cmd(cx: &mut Context, pkg: &Package, kind: Kind)
 
     info!("fingerprint at: {}", loc.display());
 
-    let new_fingerprint = try!(calculate_build_cmd_fingerprint(cx, pkg));
+    // The output is only resolved when the fingerprint is written, after the
+    // build command has run, so the directives of the current run are used.
+    let pkg_fingerprint = try!(calculate_build_cmd_fingerprint(cx, pkg));
     let new_fingerprint = Fingerprint {
         extra: String::new(),
         deps: Vec::new(),
-        local: LocalFingerprint::Precalculated(new_fingerprint),
+        local: LocalFingerprint::BuildScript {
+            pkg_name: pkg.to_string(),
+            root: pkg.root().to_path_buf(),
+            output: cx.layout(pkg, kind).build(pkg).join("output"),
+            pkg_fingerprint: pkg_fingerprint,
+        },
     };
 
     let is_fresh = try!(is_fresh(&loc, &new_fingerprint));
@@ -367,6 +392,39 @@ fn calculate_build_cmd_fingerprint(cx: &Context, pkg: &Package)
     calculate_pkg_fingerprint(cx, pkg)
 }
 
+// A build command without directives may read any file of its package. Listed
+// paths are relative to the package root, and a missing path is recorded as
+// such.
+fn build_script_fingerprint(pkg_name: &str, root: &Path, output: &Path,
+                            pkg_fingerprint: &str) -> CargoResult<String> {
+    let mut contents = String::new();
+    match File::open(output) {
+        Ok(mut f) => { try!(f.read_to_string(&mut contents)); }
+        Err(..) => return Ok(pkg_fingerprint.to_string()),
+    }
+    let output = try!(BuildOutput::parse(&contents, pkg_name));
+    if output.rerun_if_changed.is_empty() &&
+       output.rerun_if_env_changed.is_empty() {
+        return Ok(pkg_fingerprint.to_string())
+    }
+
+    let files = if output.rerun_if_changed.is_empty() {
+        pkg_fingerprint.to_string()
+    } else {
+        let mtimes = output.rerun_if_changed.iter().map(|path| {
+            let mtime = fs::metadata(&root.join(path)).ok().map(|m| {
+                m.modified()
+            });
+            (path.display().to_string(), mtime)
+        }).collect::<Vec<_>>();
+        util::short_hash(&mtimes)
+    };
+    let vars = output.rerun_if_env_changed.iter().map(|var| {
+        (var.clone(), env::var(var).ok())
+    }).collect::<Vec<_>>();
+    Ok(util::short_hash(&(files, vars)))
+}
+
 fn calculate_pkg_fingerprint(cx: &Context, pkg: &Package) -> CargoResult<String> {
     let source = cx.sources
         .get(pkg.package_id().source_id())
@@ -10,6 +10,7 @@ use core::{Package, PackageId, Resolve, PackageSet};
 use util::{Config, DependencyQueue, Fresh, Dirty, Freshness};
 use util::{CargoResult, Dependency, profile};
 
+use super::custom_build::BuildState;
 use super::job::Job;
 
 /// A management structure of the entire dependency graph to compile.
@@ -106,8 +107,10 @@ impl<'a> JobQueue<'a> {
     ///
     /// This function will spawn off `config.jobs()` workers to build all of the
     /// necessary dependencies, in order. Freshness is propagated as far as
-    /// possible along each dependency chain.
-    pub fn execute(&mut self, config: &Config) -> CargoResult<()> {
+    /// possible along each dependency chain. The warnings of build scripts are
+    /// shown as soon as they have run, taken from `build_state`.
+    pub fn execute(&mut self, config: &Config, build_state: &BuildState)
+                   -> CargoResult<()> {
         let _p = profile::start("executing the job graph");
 
         // Iteratively execute the dependency graph. Each turn of this loop will
@@ -138,6 +141,9 @@ impl<'a> JobQueue<'a> {
                     state.fresh = state.fresh.combine(fresh);
                     if state.amt == 0 {
                         self.queue.finish(&(id, stage), state.fresh);
+                        if stage == Stage::RunCustomBuild {
+                            try!(build_state.emit_warnings(id, config));
+                        }
                     }
                 }
                 Err(e) => {
@@ -8,6 +8,7 @@ use std::path::{self, PathBuf};
 use std::sync::Arc;
 
 use core::{SourceMap, Package, PackageId, PackageSet, Target, Resolve};
+use core::Profiles;
 use util::{self, CargoResult, human, caused_human};
 use util::{Config, internal, ChainError, Fresh, profile, join_paths};
 
@@ -40,12 +41,20 @@ pub struct BuildConfig {
     pub target: TargetConfig,
     pub jobs: u32,
     pub requested_target: Option<String>,
+    /// The custom profile to build with, if any.
+    pub profile: Option<String>,
+    /// The custom profiles and per-package overrides of the root manifest.
+    pub profiles: Profiles,
+    /// The target of the package being built to pass extra arguments to rustc
+    /// for, along with these arguments.
+    pub target_rustc_args: Option<(Target, Vec<String>)>,
 }
 
 #[derive(Clone, Default)]
 pub struct TargetConfig {
     pub ar: Option<String>,
     pub linker: Option<String>,
+    pub rustflags: Vec<String>,
     pub overrides: HashMap<String, BuildOutput>,
 }
 
@@ -110,7 +119,11 @@ pub fn compile_targets<'a, 'b>(env: &str,
 
     try!(links::validate(deps));
 
-    let dest = uniq_target_dest(targets);
+    // The artifacts of a custom profile get a directory of their own.
+    let dest = build_config.profile.clone().unwrap_or_else(|| {
+        uniq_target_dest(targets).to_string()
+    });
+    let dest = &dest[..];
     let root = if resolve.root() == pkg.package_id() {
         pkg
     } else {
@@ -161,7 +174,7 @@ pub fn compile_targets<'a, 'b>(env: &str,
     try!(compile(targets, pkg, true, &mut cx, &mut queue));
 
     // Now that we've figured out everything that we're going to do, do it!
-    try!(queue.execute(cx.config));
+    try!(queue.execute(cx.config, &cx.build_state));
 
     let out_dir = cx.layout(pkg, Kind::Target).build_out(pkg)
                     .display().to_string();
@@ -173,11 +186,13 @@ pub fn compile_targets<'a, 'b>(env: &str,
 
     for (&(ref pkg, _), output) in cx.build_state.outputs.lock().unwrap().iter() {
         let any_dylib = output.library_links.iter().any(|l| {
-            !l.ends_with(":static") && !l.ends_with(":framework")
+            !l.ends_with(":static") && !l.ends_with(":framework") &&
+                !l.starts_with("static=") && !l.starts_with("framework=")
         });
         if !any_dylib { continue }
         for dir in output.library_paths.iter() {
-            cx.compilation.native_dirs.insert(pkg.clone(), dir.clone());
+            cx.compilation.native_dirs.insert(pkg.clone(),
+                                              custom_build::link_search_dir(dir));
         }
     }
     Ok(cx.compilation)
@@ -387,7 +402,8 @@ fn rustc(package: &Package, target: &Target,
     }).collect();
 
     // Add all relevant -L and -l flags from dependencies (now calculated and
-    // present in `state`) to the command provided
+    // present in `state`) to the command provided, along with the cfgs and
+    // environment variables the build script of the package itself asked for
     fn add_native_deps(rustc: &mut CommandPrototype,
                        build_state: &BuildMap,
                        native_lib_deps: Vec<PackageId>,
@@ -405,6 +421,14 @@ fn rustc(package: &Package, target: &Target,
                     rustc.arg("-l").arg(name);
                 }
             }
+            if id == *current_id {
+                for cfg in output.cfgs.iter() {
+                    rustc.arg("--cfg").arg(cfg);
+                }
+                for &(ref name, ref value) in output.env.iter() {
+                    rustc.env(name, value);
+                }
+            }
         }
     }
 }
@@ -448,7 +472,7 @@ fn add_plugin_deps(rustc: &mut CommandPrototype,
     for id in plugin_deps.into_iter() {
         let output = &build_state[(id, Kind::Host)];
         for path in output.library_paths.iter() {
-            search_path.push(path.clone());
+            search_path.push(custom_build::link_search_dir(path));
         }
     }
     let search_path = try!(join_paths(&search_path, var));
@@ -576,7 +600,7 @@ fn build_base_args(cx: &Context,
 
     // Despite whatever this target's profile says, we need to configure it
     // based off the profile found in the root package's targets.
-    let profile = cx.profile(target);
+    let profile = cx.profile(pkg, target);
 
     let prefer_dynamic = profile.is_for_host() ||
                          (crate_types.contains(&"dylib") &&
@@ -654,6 +678,10 @@ fn build_plugin_args(cmd: &mut CommandPrototype, cx: &Context, pkg: &Package,
         opt(cmd, "-C", "ar=", cx.ar(kind));
         opt(cmd, "-C", "linker=", cx.linker(kind));
     }
+
+    for arg in cx.rustflags_args(pkg, target, kind).iter() {
+        cmd.arg(arg);
+    }
 }
 
 fn build_deps_args(cmd: &mut CommandPrototype, target: &Target,
@@ -1,8 +1,6 @@
 use std::ffi::OsString;
 use std::path::Path;
 
-use core::Source;
-use sources::PathSource;
 use ops::{self, ExecEngine, ProcessEngine};
 use util::{CargoResult, ProcessError};
 
@@ -15,13 +13,24 @@ pub struct TestOptions<'a, 'b: 'a> {
 pub fn run_tests(manifest_path: &Path,
                  options: &TestOptions,
                  test_args: &[String]) -> CargoResult<Option<ProcessError>> {
-    let config = options.compile_opts.config;
-    let mut source = try!(PathSource::for_path(&manifest_path.parent().unwrap(),
-                                               config));
-    try!(source.update());
-
-    let mut compile = try!(ops::compile(manifest_path, &options.compile_opts));
+    let compilations = try!(ops::compile(manifest_path, &options.compile_opts));
     if options.no_run { return Ok(None) }
+
+    for compile in compilations.into_iter() {
+        match try!(run_compilation_tests(compile, options, test_args)) {
+            Some(e) => return Ok(Some(e)),
+            None => {}
+        }
+    }
+    Ok(None)
+}
+
+// Runs the tests and doc tests of the package built by `compile`.
+fn run_compilation_tests(mut compile: ops::Compilation,
+                         options: &TestOptions,
+                         test_args: &[String])
+                         -> CargoResult<Option<ProcessError>> {
+    let config = options.compile_opts.config;
     compile.tests.sort();
 
     let tarname = options.name;
@@ -0,0 +1,359 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use semver::Version;
+
+use core::registry::PackageRegistry;
+use core::dependency::Kind;
+use core::{Registry, Resolve, PackageId, Summary, Dependency, Workspace};
+use ops;
+use util::{CargoResult, Config, human, ChainError};
+
+pub struct TreeOptions<'a, 'b: 'a> {
+    pub config: &'a Config<'b>,
+    pub features: &'a [String],
+    pub no_default_features: bool,
+    pub invert: Option<&'a str>,
+    pub duplicates: bool,
+    pub format: &'a str,
+}
+
+/// One piece of a `--format` template.
+enum Chunk {
+    Raw(String),
+    Package,
+    Name,
+    Version,
+    Source,
+    Features,
+}
+
+struct Graph<'a> {
+    resolve: &'a Resolve,
+    summaries: HashMap<PackageId, Summary>,
+    features: HashMap<PackageId, HashSet<String>>,
+    format: Vec<Chunk>,
+    invert: bool,
+}
+
+/// Renders the resolved dependency graph of the package at `manifest_path` as
+/// an indented tree, as printed by `cargo tree`.
+///
+/// The graph is taken from the lockfile, which therefore must exist. The
+/// features it doesn't record are worked out from the ones requested in
+/// `options`.
+pub fn tree(manifest_path: &Path, options: &TreeOptions) -> CargoResult<String> {
+    let format = try!(parse_format(options.format));
+
+    let ws = try!(Workspace::new(manifest_path, options.config));
+    let package = ws.current();
+
+    let resolve = match try!(ops::load_pkg_lockfile(ws.root())) {
+        Some(resolve) => resolve,
+        None => return Err(human("A Cargo.lock must exist for this command")),
+    };
+
+    // The kinds of the dependencies and the features are not part of the
+    // lockfile, so the summary of every package is looked up again.
+    let mut registry = PackageRegistry::new(options.config);
+    let mut summaries = HashMap::new();
+    for id in resolve.iter() {
+        let summary = if id == package.package_id() {
+            package.summary().clone()
+        } else {
+            try!(locked_summary(&mut registry, id))
+        };
+        summaries.insert(id.clone(), summary);
+    }
+
+    let features = options.features.iter().flat_map(|s| {
+        s.split(' ')
+    }).map(|s| s.to_string()).collect::<Vec<String>>();
+    let features = locked_features(&resolve, &summaries, package.package_id(),
+                                   features, !options.no_default_features);
+
+    let graph = Graph {
+        resolve: &resolve,
+        summaries: summaries,
+        features: features,
+        format: format,
+        invert: options.invert.is_some() || options.duplicates,
+    };
+
+    let mut out = String::new();
+    if options.duplicates {
+        for (i, id) in duplicates(&resolve).iter().enumerate() {
+            if i != 0 { out.push_str("\n"); }
+            graph.print_package(&mut out, id, &mut Vec::new(),
+                                &mut HashSet::new());
+        }
+    } else {
+        let root = match options.invert {
+            Some(spec) => try!(resolve.query(spec)),
+            None => resolve.root(),
+        };
+        graph.print_package(&mut out, root, &mut Vec::new(),
+                            &mut HashSet::new());
+    }
+    Ok(out)
+}
+
+fn locked_summary(registry: &mut PackageRegistry, id: &PackageId)
+                  -> CargoResult<Summary> {
+    let dep = try!(Dependency::parse(id.name(), None, id.source_id()));
+    let summaries = try!(registry.query(&dep.lock_to(id)));
+    summaries.into_iter().find(|s| s.package_id() == id).chain_error(|| {
+        human(format!("failed to find `{}` in its source", id))
+    })
+}
+
+/// The features enabled for every package of the locked graph when `features`
+/// are requested of `root`, propagated along the edges of the graph like the
+/// resolver does.
+fn locked_features(resolve: &Resolve, summaries: &HashMap<PackageId, Summary>,
+                   root: &PackageId, features: Vec<String>,
+                   uses_default_features: bool)
+                   -> HashMap<PackageId, HashSet<String>> {
+    let mut ret: HashMap<PackageId, HashSet<String>> = HashMap::new();
+    let mut todo = vec![(root, features, uses_default_features)];
+    while let Some((id, mut features, uses_default_features)) = todo.pop() {
+        let summary = &summaries[id];
+        if uses_default_features && summary.features().contains_key("default") {
+            features.push("default".to_string());
+        }
+        let visited = ret.contains_key(id);
+        let enabled = ret.entry(id.clone()).get().unwrap_or_else(|e| {
+            e.insert(HashSet::new())
+        });
+        features.extend(enabled.iter().cloned());
+        let (features, dep_features) = feature_closure(summary, features);
+        if visited && features.len() == enabled.len() {
+            continue
+        }
+        *enabled = features;
+
+        let locked = resolve.deps(id).into_iter().flat_map(|i| i)
+                            .collect::<Vec<_>>();
+        for dep in summary.dependencies().iter() {
+            if dep.is_optional() && !enabled.contains(dep.name()) &&
+               !dep_features.contains_key(dep.name()) {
+                continue
+            }
+            // Dependencies which weren't needed, like the dev-dependencies of
+            // other packages than the root, are missing from the lockfile.
+            let dep_id = match locked.iter().find(|id| id.name() == dep.name()) {
+                Some(dep_id) => *dep_id,
+                None => continue,
+            };
+            let mut features = dep.features().to_vec();
+            if let Some(list) = dep_features.get(dep.name()) {
+                features.extend(list.iter().cloned());
+            }
+            todo.push((dep_id, features, dep.uses_default_features()));
+        }
+    }
+    ret
+}
+
+// The features of `summary` turned on by `features`, following features that
+// enable other features, and the features requested of its dependencies
+// through `dep/feature`.
+fn feature_closure(summary: &Summary, features: Vec<String>)
+                   -> (HashSet<String>, HashMap<String, Vec<String>>) {
+    let mut enabled = HashSet::new();
+    let mut dep_features = HashMap::new();
+    let mut todo = features;
+    while let Some(feature) = todo.pop() {
+        let mut parts = feature.splitn(1, '/');
+        let name = parts.next().unwrap().to_string();
+        match parts.next() {
+            Some(dep_feature) => {
+                dep_features.entry(name).get().unwrap_or_else(|e| {
+                    e.insert(Vec::new())
+                }).push(dep_feature.to_string());
+            }
+            None => {
+                if name.is_empty() || !enabled.insert(name.clone()) {
+                    continue
+                }
+                if let Some(list) = summary.features().get(&name) {
+                    todo.extend(list.iter().cloned());
+                }
+            }
+        }
+    }
+    (enabled, dep_features)
+}
+
+/// All packages of which several semver-incompatible versions are part of the
+/// graph.
+fn duplicates(resolve: &Resolve) -> Vec<PackageId> {
+    let mut by_name = HashMap::new();
+    for id in resolve.iter() {
+        by_name.entry(id.name()).get().unwrap_or_else(|e| {
+            e.insert(Vec::new())
+        }).push(id);
+    }
+
+    let mut ret = Vec::new();
+    for (_, ids) in by_name.into_iter() {
+        let compat = ids.iter().map(|id| compat_version(id.version()))
+                        .collect::<HashSet<_>>();
+        if compat.len() > 1 {
+            ret.extend(ids.into_iter().cloned());
+        }
+    }
+    ret.sort();
+    return ret;
+
+    // Versions are compatible with each other up to the first non-zero
+    // component.
+    fn compat_version(v: &Version) -> (u64, u64, u64) {
+        if v.major > 0 {
+            (v.major, 0, 0)
+        } else if v.minor > 0 {
+            (0, v.minor, 0)
+        } else {
+            (0, 0, v.patch)
+        }
+    }
+}
+
+fn parse_format(format: &str) -> CargoResult<Vec<Chunk>> {
+    let mut chunks = Vec::new();
+    let mut raw = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            raw.push(c);
+            continue
+        }
+        let mut key = String::new();
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(c) => key.push(c),
+                None => {
+                    return Err(human(format!("unterminated `{{` in format \
+                                              `{}`", format)))
+                }
+            }
+        }
+        let chunk = match &key[..] {
+            "p" => Chunk::Package,
+            "n" => Chunk::Name,
+            "v" => Chunk::Version,
+            "s" => Chunk::Source,
+            "f" => Chunk::Features,
+            _ => {
+                return Err(human(format!("unsupported placeholder `{{{}}}` in \
+                                          format `{}`", key, format)))
+            }
+        };
+        if raw.len() > 0 {
+            chunks.push(Chunk::Raw(raw));
+            raw = String::new();
+        }
+        chunks.push(chunk);
+    }
+    if raw.len() > 0 {
+        chunks.push(Chunk::Raw(raw));
+    }
+    Ok(chunks)
+}
+
+impl<'a> Graph<'a> {
+    fn format(&self, id: &PackageId) -> String {
+        let mut ret = String::new();
+        for chunk in self.format.iter() {
+            match *chunk {
+                Chunk::Raw(ref s) => ret.push_str(s),
+                Chunk::Package => ret.push_str(&id.to_string()),
+                Chunk::Name => ret.push_str(id.name()),
+                Chunk::Version => ret.push_str(&id.version().to_string()),
+                Chunk::Source => ret.push_str(&id.source_id().to_string()),
+                Chunk::Features => {
+                    let mut features = self.features.get(id).map(|f| {
+                        f.iter().map(|s| &s[..]).collect::<Vec<_>>()
+                    }).unwrap_or(Vec::new());
+                    features.sort();
+                    ret.push_str(&features.connect(","));
+                }
+            }
+        }
+        ret.trim_right().to_string()
+    }
+
+    // The packages `id` depends on through a dependency of the given kind, or
+    // with `invert` the packages depending on `id` that way.
+    fn edges(&self, id: &PackageId, kind: Kind) -> Vec<&'a PackageId> {
+        let resolve = self.resolve;
+        let mut ret = if self.invert {
+            resolve.iter().filter(|parent| {
+                resolve.deps(parent).into_iter().flat_map(|i| i)
+                       .any(|dep| dep == id) && self.has_kind(parent, id, kind)
+            }).collect::<Vec<_>>()
+        } else {
+            resolve.deps(id).into_iter().flat_map(|i| i).filter(|dep| {
+                self.has_kind(id, dep, kind)
+            }).collect::<Vec<_>>()
+        };
+        ret.sort();
+        ret
+    }
+
+    fn has_kind(&self, parent: &PackageId, dep: &PackageId, kind: Kind) -> bool {
+        self.summaries[parent].dependencies().iter().any(|d| {
+            d.name() == dep.name() && d.kind() == kind
+        })
+    }
+
+    // `levels` holds, for every ancestor of `id`, whether it was the last
+    // entry of its list, which decides how the lines below it are drawn.
+    fn print_package(&self, out: &mut String, id: &PackageId,
+                     levels: &mut Vec<bool>, printed: &mut HashSet<PackageId>) {
+        if let Some(&last) = levels.last() {
+            push_prefix(out, &levels[..levels.len() - 1]);
+            out.push_str(if last {"└── "} else {"├── "});
+        }
+        out.push_str(&self.format(id));
+
+        let kinds = [Kind::Normal, Kind::Build, Kind::Development];
+        let edges = kinds.iter().map(|&kind| {
+            (kind, self.edges(id, kind))
+        }).filter(|&(_, ref deps)| deps.len() > 0).collect::<Vec<_>>();
+
+        // Subtrees are only printed the first time they're encountered, which
+        // also keeps cycles through dev-dependencies from recursing forever.
+        if !printed.insert(id.clone()) && edges.len() > 0 {
+            out.push_str(" (*)\n");
+            return
+        }
+        out.push_str("\n");
+
+        for (kind, deps) in edges.into_iter() {
+            match kind {
+                Kind::Normal => {}
+                Kind::Build => {
+                    push_prefix(out, levels);
+                    out.push_str("[build-dependencies]\n");
+                }
+                Kind::Development => {
+                    push_prefix(out, levels);
+                    out.push_str("[dev-dependencies]\n");
+                }
+            }
+            for (i, dep) in deps.iter().enumerate() {
+                levels.push(i == deps.len() - 1);
+                self.print_package(out, dep, levels, printed);
+                levels.pop();
+            }
+        }
+
+        fn push_prefix(out: &mut String, levels: &[bool]) {
+            for &last in levels.iter() {
+                out.push_str(if last {"    "} else {"│   "});
+            }
+        }
+    }
+}
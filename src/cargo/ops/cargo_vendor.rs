@@ -0,0 +1,150 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::Path;
+
+use rustc_serialize::hex::ToHex;
+use rustc_serialize::json;
+
+use core::registry::PackageRegistry;
+use core::{Source, Package, PackageId, Workspace};
+use ops;
+use sources::PathSource;
+use sources::directory::CHECKSUM_FILE;
+use util::{CargoResult, Config, Sha256, human, ChainError};
+
+pub struct VendorOptions<'a, 'b: 'a> {
+    pub config: &'a Config<'b>,
+    pub destination: &'a Path,
+}
+
+/// Contents of the checksum file of a vendored package, as read back by
+/// `DirectorySource`.
+#[derive(RustcEncodable)]
+struct Checksums {
+    // The sha256 of every file, keyed by its path relative to the package.
+    files: BTreeMap<String, String>,
+    // The checksum of the package as recorded in the lockfile, if any.
+    package: Option<String>,
+}
+
+/// Copies every registry and git package the package at `manifest_path`
+/// depends on into `<destination>/<name>-<version>`, next to a checksum file
+/// listing the copied files. The dependencies of all members of its workspace
+/// are vendored at once.
+///
+/// Returns the `.cargo/config` snippet which makes later builds use the
+/// vendored copies: the destination becomes a directory source standing in
+/// for the registry, and git packages are listed as path overrides. A
+/// relative `destination` is taken relative to the root of the workspace.
+pub fn vendor(manifest_path: &Path, options: &VendorOptions)
+              -> CargoResult<String> {
+    let config = options.config;
+    let ws = try!(Workspace::new(manifest_path, config));
+    let package = ws.root();
+
+    let mut registry = PackageRegistry::new(config);
+    let resolve = try!(ops::resolve_ws(&mut registry, &ws, config));
+
+    // Path dependencies are already local.
+    let ids = resolve.iter().filter(|id| {
+        !id.source_id().is_path()
+    }).cloned().collect::<Vec<PackageId>>();
+    let mut packages = try!(registry.get(&ids).chain_error(|| {
+        human("unable to get packages from source")
+    }));
+    packages.sort_by(|a, b| a.package_id().cmp(b.package_id()));
+
+    let mut dirs = HashMap::new();
+    for pkg in packages.iter() {
+        let dir = format!("{}-{}", pkg.name(), pkg.version());
+        if let Some(other) = dirs.insert(dir.clone(), pkg.package_id()) {
+            return Err(human(format!("failed to vendor `{}` and `{}` as both \
+                                      would be copied to `{}`", other,
+                                     pkg.package_id(), dir)))
+        }
+    }
+
+    let dst = package.root().join(options.destination);
+    try!(fs::create_dir_all(&dst));
+
+    // Drop the packages vendored by a previous run which are no longer part of
+    // the graph.
+    for entry in try!(fs::read_dir(&dst)) {
+        let path = try!(entry).path();
+        let stale = path.file_name().and_then(|s| s.to_str()).map(|name| {
+            !dirs.contains_key(name)
+        }).unwrap_or(false);
+        if stale && fs::metadata(&path.join(CHECKSUM_FILE)).is_ok() {
+            try!(fs::remove_dir_all(&path));
+        }
+    }
+
+    let mut git_dirs = Vec::new();
+    for pkg in packages.iter() {
+        let dir = format!("{}-{}", pkg.name(), pkg.version());
+        try!(config.shell().status("Vendoring", pkg.package_id()));
+        try!(vendor_package(pkg, resolve.checksum(pkg.package_id()),
+                            &dst.join(&dir), config).chain_error(|| {
+            human(format!("failed to vendor `{}`", pkg.package_id()))
+        }));
+        if pkg.package_id().source_id().is_git() {
+            git_dirs.push(options.destination.join(&dir));
+        }
+    }
+
+    // The vendored packages stand in for the registry as a directory source,
+    // while git dependencies are overridden one by one.
+    let mut snippet = "# Use the vendored copies of the dependencies instead \
+                       of their sources\n".to_string();
+    if git_dirs.len() > 0 {
+        snippet.push_str("paths = [\n");
+        for dir in git_dirs.iter() {
+            snippet.push_str(&format!("    \"{}\",\n", dir.display()));
+        }
+        snippet.push_str("]\n");
+    }
+    if packages.iter().any(|p| !p.package_id().source_id().is_git()) {
+        snippet.push_str(&format!("[registry]\ndirectory = \"{}\"\n",
+                                  options.destination.display()));
+    }
+    Ok(snippet)
+}
+
+fn vendor_package(pkg: &Package, checksum: Option<&str>, dst: &Path,
+                  config: &Config) -> CargoResult<()> {
+    // Already taken from the vendored copy through a directory source.
+    if pkg.root() == dst { return Ok(()) }
+    if fs::metadata(dst).is_ok() {
+        try!(fs::remove_dir_all(dst));
+    }
+
+    let mut src = PathSource::new(pkg.root(), pkg.package_id().source_id(),
+                                  config);
+    try!(src.update());
+
+    let mut files = BTreeMap::new();
+    for file in try!(src.list_files(pkg)).iter() {
+        let relative = file.relative_from(pkg.root()).unwrap();
+        let name = try!(relative.to_str().chain_error(|| {
+            human(format!("invalid utf-8 filename: {}", relative.display()))
+        }));
+        let to = dst.join(relative);
+        try!(fs::create_dir_all(to.parent().unwrap()));
+        try!(fs::copy(file, &to));
+
+        let mut contents = Vec::new();
+        try!(try!(File::open(file)).read_to_end(&mut contents));
+        let mut state = Sha256::new();
+        state.update(&contents);
+        files.insert(name.replace("\\", "/"), state.finish().to_hex());
+    }
+
+    let checksums = Checksums {
+        files: files,
+        package: checksum.map(|s| s.to_string()),
+    };
+    try!(try!(File::create(&dst.join(CHECKSUM_FILE)))
+             .write_all(json::encode(&checksums).unwrap().as_bytes()));
+    Ok(())
+}
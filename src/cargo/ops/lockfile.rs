@@ -6,7 +6,7 @@ use rustc_serialize::{Encodable, Decodable};
 use toml::{self, Encoder, Value};
 
 use core::{Resolve, resolver, Package, SourceId};
-use util::{CargoResult, ChainError, human};
+use util::{CargoResult, ChainError, Config, human};
 use util::toml as cargo_toml;
 
 pub fn load_pkg_lockfile(pkg: &Package) -> CargoResult<Option<Resolve>> {
@@ -33,12 +33,40 @@ pub fn load_lockfile(path: &Path, sid: &SourceId) -> CargoResult<Option<Resolve>
     Ok(Some(try!(v.to_resolve(sid))))
 }
 
-pub fn write_pkg_lockfile(pkg: &Package, resolve: &Resolve) -> CargoResult<()> {
+/// Writes `resolve` to the lockfile of `pkg`.
+///
+/// The file is left untouched if it already has the same contents, and it is
+/// an error to change it when `config` doesn't allow to (`--frozen`).
+pub fn write_pkg_lockfile(pkg: &Package, resolve: &Resolve, config: &Config)
+                          -> CargoResult<()> {
     let loc = pkg.root().join("Cargo.lock");
-    write_lockfile(&loc, resolve)
+    let out = serialize_resolve(resolve);
+
+    let mut prev = String::new();
+    let unchanged = File::open(&loc).and_then(|mut f| {
+        f.read_to_string(&mut prev)
+    }).is_ok() && prev == out;
+    if unchanged {
+        return Ok(())
+    }
+
+    if !config.lock_update_allowed() {
+        return Err(human(format!("the lock file {} needs to be updated but \
+                                  --frozen was passed to prevent this",
+                                 loc.display())))
+    }
+
+    try!(try!(File::create(&loc)).write_all(out.as_bytes()));
+    Ok(())
 }
 
 pub fn write_lockfile(dst: &Path, resolve: &Resolve) -> CargoResult<()> {
+    let out = serialize_resolve(resolve);
+    try!(try!(File::create(dst)).write_all(out.as_bytes()));
+    Ok(())
+}
+
+fn serialize_resolve(resolve: &Resolve) -> String {
     let mut e = Encoder::new();
     resolve.encode(&mut e).unwrap();
 
@@ -63,13 +91,12 @@ pub fn write_lockfile(dst: &Path, resolve: &Resolve) -> CargoResult<()> {
     match e.toml.get(&"metadata".to_string()) {
         Some(metadata) => {
             out.push_str("[metadata]\n");
-            out.push_str(&metadata.to_string());
+            emit_metadata(metadata.as_table().unwrap(), &mut out);
         }
         None => {}
     }
 
-    try!(try!(File::create(dst)).write_all(out.as_bytes()));
-    Ok(())
+    out
 }
 
 fn emit_package(dep: &toml::Table, out: &mut String) {
@@ -96,6 +123,21 @@ fn emit_package(dep: &toml::Table, out: &mut String) {
     }
 }
 
+fn emit_metadata(metadata: &toml::Table, out: &mut String) {
+    for (key, value) in metadata.iter() {
+        // Checksum keys contain spaces and need to be quoted.
+        let bare = key.chars().all(|c| {
+            c.is_alphanumeric() || c == '_' || c == '-'
+        });
+        if bare {
+            out.push_str(&format!("{} = {}\n", key, value));
+        } else {
+            out.push_str(&format!("{} = {}\n", Value::String(key.clone()),
+                                  value));
+        }
+    }
+}
+
 fn lookup<'a>(table: &'a toml::Table, key: &str) -> &'a toml::Value {
     table.get(key).expect(&format!("didn't find {}", key))
 }
@@ -1,6 +1,6 @@
 pub use self::cargo_clean::{clean, CleanOptions};
 #[cfg(feature = "debian")] pub use self::cargo_debianize::{debianize, DebianizeOptions};
-pub use self::cargo_compile::{compile, compile_pkg, CompileOptions};
+pub use self::cargo_compile::{compile, compile_ws, target_env, CompileOptions};
 pub use self::cargo_read_manifest::{read_manifest,read_package,read_packages};
 pub use self::cargo_rustc::{compile_targets, Compilation, Layout, Kind, rustc_version};
 pub use self::cargo_rustc::{Context, LayoutProxy};
@@ -22,7 +22,11 @@ pub use self::registry::{registry_login, search, http_proxy_exists, http_handle}
 pub use self::registry::{modify_owners, yank, OwnersOptions};
 pub use self::cargo_fetch::{fetch};
 pub use self::cargo_pkgid::pkgid;
-pub use self::resolve::{resolve_pkg, resolve_with_previous};
+pub use self::cargo_output_metadata::{output_metadata, OutputMetadataOptions};
+pub use self::cargo_output_metadata::{ExportInfo, METADATA_VERSION};
+pub use self::cargo_tree::{tree, TreeOptions};
+pub use self::cargo_vendor::{vendor, VendorOptions};
+pub use self::resolve::{resolve_ws, resolve_with_previous};
 
 mod cargo_clean;
 mod cargo_compile;
@@ -31,12 +35,15 @@ mod cargo_doc;
 mod cargo_fetch;
 mod cargo_generate_lockfile;
 mod cargo_new;
+mod cargo_output_metadata;
 mod cargo_package;
 mod cargo_pkgid;
 mod cargo_read_manifest;
 mod cargo_run;
 mod cargo_rustc;
 mod cargo_test;
+mod cargo_tree;
+mod cargo_vendor;
 mod lockfile;
 mod registry;
 mod resolve;
@@ -1,48 +1,56 @@
 use std::collections::{HashMap, HashSet};
 
-use core::{Package, PackageId, SourceId};
+use core::{Package, PackageId, SourceId, Workspace};
 use core::registry::PackageRegistry;
-use core::resolver::{self, Resolve, Method};
+use core::resolver::{self, Resolve, Method, ResolveOptions};
 use ops;
-use util::CargoResult;
+use util::{CargoResult, Config, human};
 
-/// Resolve all dependencies for the specified `package` using the previous
-/// lockfile as a guide if present.
+/// Resolve all dependencies of the members of the workspace `ws` using the
+/// previous lockfile of the workspace as a guide if present.
 ///
 /// This function will also generate a write the result of resolution as a new
-/// lockfile.
-pub fn resolve_pkg(registry: &mut PackageRegistry, package: &Package)
-                   -> CargoResult<Resolve> {
-    let prev = try!(ops::load_pkg_lockfile(package));
-    let resolve = try!(resolve_with_previous(registry, package,
+/// lockfile, unless `config` forbids changing it.
+pub fn resolve_ws(registry: &mut PackageRegistry, ws: &Workspace,
+                  config: &Config) -> CargoResult<Resolve> {
+    let prev = try!(ops::load_pkg_lockfile(ws.root()));
+    let resolve = try!(resolve_with_previous(registry, ws, ws.members(),
                                              Method::Everything,
+                                             ResolveOptions::default(),
                                              prev.as_ref(), None));
-    try!(ops::write_pkg_lockfile(package, &resolve));
+    try!(ops::write_pkg_lockfile(ws.root(), &resolve, config));
     Ok(resolve)
 }
 
-/// Resolve all dependencies for a package using an optional previous instance
-/// of resolve to guide the resolution process.
+/// Resolve all dependencies of `roots`, members of the workspace `ws`, using
+/// an optional previous instance of resolve to guide the resolution process.
+/// The first of the `roots` becomes the root of the resolved graph.
 ///
 /// This also takes an optional hash set, `to_avoid`, which is a list of package
 /// ids that should be avoided when consulting the previous instance of resolve
 /// (often used in pairings with updates).
 ///
+/// The options given in `opts` decide which versions are preferred for
+/// everything that isn't locked by the previous resolve.
+///
 /// The previous resolve normally comes from a lockfile. This function does not
 /// read or write lockfiles from the filesystem.
 pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
-                                 package: &Package,
+                                 ws: &Workspace,
+                                 roots: &[Package],
                                  method: Method,
+                                 opts: ResolveOptions,
                                  previous: Option<&'a Resolve>,
                                  to_avoid: Option<&HashSet<&'a PackageId>>)
                                  -> CargoResult<Resolve> {
-    let root = package.package_id().source_id().clone();
+    let root = ws.root().package_id().source_id().clone();
     try!(registry.add_sources(&[root]));
+    registry.add_replacements(ws.root().manifest().replace());
 
     // Here we place an artificial limitation that all non-registry sources
-    // cannot be locked at more than one revision. This means that if a git
-    // repository provides more than one package, they must all be updated in
-    // step when any of them are updated.
+    // (directories of vendored packages aside) cannot be locked at more than
+    // one revision. This means that if a git repository provides more than one
+    // package, they must all be updated in step when any of them are updated.
     //
     // TODO: This seems like a hokey reason to single out the registry as being
     //       different
@@ -51,7 +59,7 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
         Some(set) => {
             for package_id in set.iter() {
                 let source = package_id.source_id();
-                if !source.is_registry() {
+                if !source.is_registry() && !source.is_directory() {
                     to_avoid_sources.insert(source);
                 }
             }
@@ -59,55 +67,65 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
         None => {}
     }
 
-    let summary = package.summary().clone();
-    let summary = match previous {
-        Some(r) => {
-            // In the case where a previous instance of resolve is available, we
-            // want to lock as many packages as possible to the previous version
-            // without disturbing the graph structure. To this end we perform
-            // two actions here:
-            //
-            // 1. We inform the package registry of all locked packages. This
-            //    involves informing it of both the locked package's id as well
-            //    as the versions of all locked dependencies. The registry will
-            //    then takes this information into account when it is queried.
-            //
-            // 2. The specified package's summary will have its dependencies
-            //    modified to their precise variants. This will instruct the
-            //    first step of the resolution process to not query for ranges
-            //    but rather precise dependency versions.
-            //
-            //    This process must handle altered dependencies, however, as
-            //    it's possible for a manifest to change over time to have
-            //    dependencies added, removed, or modified to different version
-            //    ranges. To deal with this, we only actually lock a dependency
-            //    to the previously resolved version if the dependency listed
-            //    still matches the locked version.
-            for node in r.iter().filter(|p| keep(p, to_avoid, &to_avoid_sources)) {
-                let deps = r.deps(node).into_iter().flat_map(|i| i)
-                            .filter(|p| keep(p, to_avoid, &to_avoid_sources))
-                            .map(|p| p.clone()).collect();
-                registry.register_lock(node.clone(), deps);
-            }
-
-            let map = r.deps(r.root()).into_iter().flat_map(|i| i).filter(|p| {
-                keep(p, to_avoid, &to_avoid_sources)
-            }).map(|d| {
-                (d.name(), d)
-            }).collect::<HashMap<_, _>>();
-            summary.map_dependencies(|d| {
-                match map.get(d.name()) {
-                    Some(&lock) if d.matches_id(lock) => d.lock_to(lock),
-                    _ => d,
-                }
-            })
+    // In the case where a previous instance of resolve is available, we want
+    // to lock as many packages as possible to the previous version without
+    // disturbing the graph structure. To this end we perform two actions here:
+    //
+    // 1. We inform the package registry of all locked packages. This involves
+    //    informing it of both the locked package's id as well as the versions
+    //    of all locked dependencies. The registry will then takes this
+    //    information into account when it is queried.
+    //
+    // 2. The summaries of the specified packages will have their dependencies
+    //    modified to their precise variants. This will instruct the first step
+    //    of the resolution process to not query for ranges but rather precise
+    //    dependency versions.
+    //
+    //    This process must handle altered dependencies, however, as it's
+    //    possible for a manifest to change over time to have dependencies
+    //    added, removed, or modified to different version ranges. To deal with
+    //    this, we only actually lock a dependency to the previously resolved
+    //    version if the dependency listed still matches the locked version.
+    if let Some(r) = previous {
+        for node in r.iter().filter(|p| keep(p, to_avoid, &to_avoid_sources)) {
+            let deps = r.deps(node).into_iter().flat_map(|i| i)
+                        .filter(|p| keep(p, to_avoid, &to_avoid_sources))
+                        .map(|p| p.clone()).collect();
+            registry.register_lock(node.clone(), deps);
         }
-        None => summary,
-    };
+    }
 
-    let mut resolved = try!(resolver::resolve(&summary, method, registry));
+    let summaries = roots.iter().map(|package| {
+        let summary = package.summary().clone();
+        let r = match previous {
+            Some(r) => r,
+            None => return summary,
+        };
+        // The root of the workspace is locked through the root of the previous
+        // resolve, whose version may have been changed since.
+        let id = if package == ws.root() {r.root()} else {package.package_id()};
+        let map = r.deps(id).into_iter().flat_map(|i| i).filter(|p| {
+            keep(p, to_avoid, &to_avoid_sources)
+        }).map(|d| {
+            (d.name(), d)
+        }).collect::<HashMap<_, _>>();
+        summary.map_dependencies(|d| {
+            match map.get(d.name()) {
+                Some(&lock) if d.matches_id(lock) => d.lock_to(lock),
+                _ => d,
+            }
+        })
+    }).collect::<Vec<_>>();
+
+    let mut resolved = try!(resolver::resolve_roots(&summaries, method, opts,
+                                                     registry));
     match previous {
-        Some(r) => resolved.copy_metadata(r),
+        Some(r) => {
+            try!(check_checksums(r, &resolved, |id| {
+                keep(&id, to_avoid, &to_avoid_sources)
+            }));
+            resolved.copy_metadata(r);
+        }
         None => {}
     }
     return Ok(resolved);
@@ -122,3 +140,31 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
         }
     }
 }
+
+/// Makes sure that no package locked by the previous lockfile changed its
+/// contents since the checksums in it were recorded, e.g. by a rewritten
+/// registry index or a force-pushed git repository. Packages being updated
+/// are not `locked` and may legitimately change.
+fn check_checksums<'a, F>(previous: &'a Resolve, resolved: &Resolve,
+                          locked: F) -> CargoResult<()>
+    where F: Fn(&'a PackageId) -> bool
+{
+    for id in resolved.iter() {
+        let id = match previous.iter().find(|p| *p == id) {
+            Some(id) if locked(id) => id,
+            _ => continue,
+        };
+
+        match (previous.checksum(id), resolved.checksum(id)) {
+            (Some(old), Some(new)) if old != new => {
+                return Err(human(format!("checksum for `{}` changed between \
+                                          lock files\n\nexpected: {}\n\
+                                          found: {}\n\nthe source of the \
+                                          package may have been tampered \
+                                          with", id, old, new)))
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
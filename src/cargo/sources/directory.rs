@@ -0,0 +1,152 @@
+//! A `Source` for a local directory of unpacked packages, as written by
+//! `cargo vendor`.
+//!
+//! Every subdirectory containing a `Cargo.toml` is one package. Next to the
+//! manifest, a `.cargo-checksum.json` file records the sha256 of each file of
+//! the package as well as the checksum of the package itself, if the source it
+//! was copied from had one:
+//!
+//! ```notrust
+//! {
+//!     "files": { "Cargo.toml": "<sha256>", "src/lib.rs": "<sha256>" },
+//!     "package": "<checksum>"
+//! }
+//! ```
+//!
+//! The contents of a directory source are expected never to change, so the
+//! files of a package are verified against these checksums before it is used.
+
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use rustc_serialize::hex::ToHex;
+use rustc_serialize::json;
+
+use core::{Package, PackageId, Summary, SourceId, Source, Dependency, Registry};
+use ops;
+use util::{CargoResult, Config, Sha256, human, ChainError};
+
+/// The name of the checksum file of every package.
+pub const CHECKSUM_FILE: &'static str = ".cargo-checksum.json";
+
+pub struct DirectorySource<'a, 'b: 'a> {
+    id: SourceId,
+    root: PathBuf,
+    packages: HashMap<PackageId, (Package, Checksum)>,
+    config: &'a Config<'b>,
+}
+
+#[derive(RustcDecodable)]
+struct Checksum {
+    package: Option<String>,
+    files: HashMap<String, String>,
+}
+
+impl<'a, 'b> DirectorySource<'a, 'b> {
+    pub fn new(path: &Path, id: &SourceId, config: &'a Config<'b>)
+               -> DirectorySource<'a, 'b> {
+        DirectorySource {
+            id: id.clone(),
+            root: path.to_path_buf(),
+            packages: HashMap::new(),
+            config: config,
+        }
+    }
+
+    fn verify(&self, pkg: &Package, checksum: &Checksum) -> CargoResult<()> {
+        for (file, expected) in checksum.files.iter() {
+            let path = pkg.root().join(file);
+            let mut contents = Vec::new();
+            try!(File::open(&path).and_then(|mut f| {
+                f.read_to_end(&mut contents)
+            }).chain_error(|| {
+                human(format!("failed to read `{}`", path.display()))
+            }));
+            let actual = {
+                let mut state = Sha256::new();
+                state.update(&contents);
+                state.finish().to_hex()
+            };
+            if actual != *expected {
+                return Err(human(format!("the listed checksum of `{}` has \
+                                          changed:\nexpected: {}\nactual:   {}",
+                                         path.display(), expected, actual)))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'b> Debug for DirectorySource<'a, 'b> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "DirectorySource {{ root: {:?} }}", self.root)
+    }
+}
+
+impl<'a, 'b> Registry for DirectorySource<'a, 'b> {
+    fn query(&mut self, dep: &Dependency) -> CargoResult<Vec<Summary>> {
+        let mut summaries = self.packages.values().map(|&(ref pkg, ref cksum)| {
+            let summary = pkg.summary().clone();
+            match cksum.package {
+                Some(ref s) => summary.set_checksum(s.clone()),
+                None => summary,
+            }
+        }).collect::<Vec<_>>();
+        summaries.query(dep)
+    }
+}
+
+impl<'a, 'b> Source for DirectorySource<'a, 'b> {
+    fn update(&mut self) -> CargoResult<()> {
+        self.packages.clear();
+        let entries = try!(fs::read_dir(&self.root).chain_error(|| {
+            human(format!("failed to read directory source at `{}`",
+                          self.root.display()))
+        }));
+        for entry in entries {
+            let path = try!(entry).path();
+            let manifest = path.join("Cargo.toml");
+            if fs::metadata(&manifest).is_err() { continue }
+
+            let (pkg, _) = try!(ops::read_package(&manifest, &self.id,
+                                                  self.config));
+            let cksum_path = path.join(CHECKSUM_FILE);
+            let mut contents = String::new();
+            try!(File::open(&cksum_path).and_then(|mut f| {
+                f.read_to_string(&mut contents)
+            }).chain_error(|| {
+                human(format!("failed to load checksum file of `{}` at `{}`",
+                              pkg.package_id(), cksum_path.display()))
+            }));
+            let cksum: Checksum = try!(json::decode(&contents).chain_error(|| {
+                human(format!("failed to decode `{}`", cksum_path.display()))
+            }));
+            self.packages.insert(pkg.package_id().clone(), (pkg, cksum));
+        }
+        Ok(())
+    }
+
+    fn download(&mut self, packages: &[PackageId]) -> CargoResult<()> {
+        for id in packages.iter().filter(|id| id.source_id() == &self.id) {
+            let &(ref pkg, ref cksum) = try!(self.packages.get(id).chain_error(|| {
+                human(format!("failed to find `{}` in the directory source at \
+                               `{}`", id, self.root.display()))
+            }));
+            try!(self.verify(pkg, cksum));
+        }
+        Ok(())
+    }
+
+    fn get(&self, packages: &[PackageId]) -> CargoResult<Vec<Package>> {
+        Ok(packages.iter().filter_map(|id| self.packages.get(id)).map(|p| {
+            p.0.clone()
+        }).collect())
+    }
+
+    fn fingerprint(&self, pkg: &Package) -> CargoResult<String> {
+        Ok(pkg.package_id().version().to_string())
+    }
+}
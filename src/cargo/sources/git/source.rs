@@ -8,9 +8,9 @@ use url::{self, Url};
 use core::source::{Source, SourceId};
 use core::GitReference;
 use core::{Package, PackageId, Summary, Registry, Dependency};
-use util::{CargoResult, Config, to_hex};
+use util::{CargoResult, Config, human, to_hex};
 use sources::PathSource;
-use sources::git::utils::{GitRemote, GitRevision};
+use sources::git::utils::{GitRemote, GitRevision, GitDatabase};
 
 /* TODO: Refactor GitSource to delegate to a PathSource
  */
@@ -67,6 +67,30 @@ impl<'a, 'b> GitSource<'a, 'b> {
     }
 
     pub fn url(&self) -> &Url { self.remote.url() }
+
+    /// Makes sure that the revision recorded in the lockfile is still part of
+    /// the history of the branch, tag or revision it was resolved from, as
+    /// fetched from upstream. Anything else means the repository was
+    /// rewritten since, e.g. by a force-push.
+    fn check_locked_rev(&self, repo: &GitDatabase) -> CargoResult<()> {
+        // Abbreviated revisions only come from `cargo update --precise`.
+        let locked = match self.source_id.precise() {
+            Some(locked) if locked.len() == 40 => locked,
+            _ => return Ok(()),
+        };
+        let upstream = try!(repo.rev_for(self.source_id.git_reference().unwrap()));
+        let contained = match repo.rev_for(&GitReference::Rev(locked.to_string())) {
+            Ok(rev) => try!(repo.is_ancestor(&rev, &upstream)),
+            Err(..) => false,
+        };
+        if contained {
+            return Ok(())
+        }
+        Err(human(format!("revision {} of git repository `{}` locked in \
+                           Cargo.lock is no longer part of its history, \
+                           run `cargo update` to accept the rewritten \
+                           history", locked, self.remote.url())))
+    }
 }
 
 fn ident(url: &Url) -> String {
@@ -156,7 +180,11 @@ impl<'a, 'b> Registry for GitSource<'a, 'b> {
     fn query(&mut self, dep: &Dependency) -> CargoResult<Vec<Summary>> {
         let src = self.path_source.as_mut()
                       .expect("BUG: update() must be called before query()");
-        src.query(dep)
+        // The revision identifies the contents of every package in the
+        // repository, so it serves as their checksum.
+        let rev = self.rev.as_ref().unwrap().to_string();
+        let summaries = try!(src.query(dep));
+        Ok(summaries.into_iter().map(|s| s.set_checksum(rev.clone())).collect())
     }
 }
 
@@ -166,12 +194,24 @@ impl<'a, 'b> Source for GitSource<'a, 'b> {
         let should_update = actual_rev.is_err() ||
                             self.source_id.precise().is_none();
 
+        // Without network access a previously fetched database is used as is,
+        // as long as it contains the requested revision.
+        if actual_rev.is_err() && !self.config.network_allowed() {
+            return Err(human(format!("cannot update git repository `{}` \
+                                      without network access (--frozen or \
+                                      --offline was passed) as the requested \
+                                      revision was never fetched",
+                                     self.remote.url())))
+        }
+        let should_update = should_update && self.config.network_allowed();
+
         let (repo, actual_rev) = if should_update {
             try!(self.config.shell().status("Updating",
                 format!("git repository `{}`", self.remote.url())));
 
             trace!("updating git source `{:?}`", self.remote);
             let repo = try!(self.remote.checkout(&self.db_path));
+            try!(self.check_locked_rev(&repo));
             let rev = try!(repo.rev_for(&self.reference));
             (repo, rev)
         } else {
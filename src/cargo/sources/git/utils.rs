@@ -216,6 +216,14 @@ impl GitDatabase {
         Ok(GitRevision(id))
     }
 
+    /// Returns whether `rev` is `tip` or one of its ancestors.
+    pub fn is_ancestor(&self, rev: &GitRevision, tip: &GitRevision)
+                       -> CargoResult<bool> {
+        let mut walk = try!(self.repo.revwalk());
+        try!(walk.push(tip.0));
+        Ok(walk.any(|id| id == rev.0))
+    }
+
     pub fn has_ref(&self, reference: &str) -> CargoResult<()> {
         try!(self.repo.revparse_single(reference));
         Ok(())
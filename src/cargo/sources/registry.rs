@@ -126,6 +126,15 @@
 //! already been downloaded and unpacked. This caching allows us to only
 //! download a package when absolutely necessary.
 //!
+//! # Local Registries
+//!
+//! A local registry is a directory containing an `index` directory laid out as
+//! described above, next to the `<pkg>-<version>.crate` tarballs of every
+//! package listed in it. It is selected with the `registry.local-registry`
+//! configuration key and never touches the network: the index is used as is,
+//! and the tarballs are verified against the checksums of the index before
+//! they are unpacked like downloaded ones.
+//!
 //! # Filesystem Hierarchy
 //!
 //! Overall, the `$HOME/.cargo` looks like this when talking about the registry:
@@ -232,9 +241,22 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
         let hash = hex::short_hash(source_id);
         let ident = source_id.url().host().unwrap().to_string();
         let part = format!("{}-{}", ident, hash);
+
+        // A local registry already has its index and tarballs on disk, only
+        // the unpacked sources go to the usual location.
+        let (checkout_path, cache_path) = if source_id.is_local_registry() {
+            let root = match source_id.url().to_file_path::<PathBuf>() {
+                Ok(p) => p,
+                Err(()) => panic!("local registries cannot be remote"),
+            };
+            (root.join("index"), root)
+        } else {
+            (config.registry_index_path().join(&part),
+             config.registry_cache_path().join(&part))
+        };
         RegistrySource {
-            checkout_path: config.registry_index_path().join(&part),
-            cache_path: config.registry_cache_path().join(&part),
+            checkout_path: checkout_path,
+            cache_path: cache_path,
             src_path: config.registry_source_path().join(&part),
             config: config,
             source_id: source_id.clone(),
@@ -302,6 +324,11 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
         let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
         let dst = self.cache_path.join(&filename);
         if fs::metadata(&dst).is_ok() { return Ok(dst) }
+        if !self.config.network_allowed() {
+            return Err(human(format!("cannot download `{}` without network \
+                                      access (--frozen or --offline was \
+                                      passed)", pkg)))
+        }
         try!(self.config.shell().status("Downloading", pkg));
 
         try!(fs::create_dir_all(dst.parent().unwrap()));
@@ -319,7 +346,35 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
                                         url, resp)))
         }
 
-        // Verify what we just downloaded
+        try!(self.verify_checksum(pkg, resp.get_body()));
+
+        try!(try!(File::create(&dst)).write_all(resp.get_body()));
+        Ok(dst)
+    }
+
+    /// Find the tarball of the given package in a local registry.
+    ///
+    /// Tarballs are kept next to the index and never change, but are verified
+    /// all the same as they are not downloaded through this source.
+    fn local_package(&mut self, pkg: &PackageId) -> CargoResult<PathBuf> {
+        let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
+        let path = self.cache_path.join(&filename);
+        let mut contents = Vec::new();
+        try!(File::open(&path).and_then(|mut f| {
+            f.read_to_end(&mut contents)
+        }).chain_error(|| {
+            human(format!("failed to read `{}` from the local registry at \
+                           `{}`", filename, self.cache_path.display()))
+        }));
+        try!(self.verify_checksum(pkg, &contents));
+        Ok(path)
+    }
+
+    /// Verify the contents of the tarball of a package. The checksum from the
+    /// index has already been checked against the one in the lockfile (if
+    /// any) during resolution.
+    fn verify_checksum(&self, pkg: &PackageId, contents: &[u8])
+                       -> CargoResult<()> {
         let expected = self.hashes.get(&(pkg.name().to_string(),
                                          pkg.version().to_string()));
         let expected = try!(expected.chain_error(|| {
@@ -327,16 +382,14 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
         }));
         let actual = {
             let mut state = Sha256::new();
-            state.update(resp.get_body());
+            state.update(contents);
             state.finish()
         };
         if actual.to_hex() != *expected {
             return Err(human(format!("Failed to verify the checksum of `{}`",
                                      pkg)))
         }
-
-        try!(try!(File::create(&dst)).write_all(resp.get_body()));
-        Ok(dst)
+        Ok(())
     }
 
     /// Unpacks a downloaded package into a location where it's ready to be
@@ -410,8 +463,9 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
             self.parse_registry_dependency(dep)
         }).collect();
         let deps = try!(deps);
-        self.hashes.insert((name, vers), cksum);
-        Ok((try!(Summary::new(pkgid, deps, features)), yanked.unwrap_or(false)))
+        self.hashes.insert((name, vers), cksum.clone());
+        let summary = try!(Summary::new(pkgid, deps, features));
+        Ok((summary.set_checksum(cksum), yanked.unwrap_or(false)))
     }
 
     /// Converts an encoded dependency in the registry to a cargo dependency
@@ -440,6 +494,30 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
     fn do_update(&mut self) -> CargoResult<()> {
         if self.updated { return Ok(()) }
 
+        // A local registry is always up to date, as long as it exists.
+        if self.source_id.is_local_registry() {
+            if fs::metadata(&self.checkout_path).is_err() {
+                return Err(human(format!("local registry index not found at \
+                                          `{}`", self.checkout_path.display())))
+            }
+            self.updated = true;
+            return Ok(())
+        }
+
+        // Without network access the index is used as it was last fetched,
+        // which only works if it was fetched at all.
+        if !self.config.network_allowed() {
+            if fs::metadata(&self.checkout_path.join("config.json")).is_err() {
+                return Err(human(format!("cannot update registry `{}` without \
+                                          network access (--frozen or \
+                                          --offline was passed) as its index \
+                                          was never fetched",
+                                         self.source_id.url())))
+            }
+            self.updated = true;
+            return Ok(())
+        }
+
         try!(self.config.shell().status("Updating",
              format!("registry `{}`", self.source_id.url())));
         let repo = try!(self.open());
@@ -518,19 +596,28 @@ impl<'a, 'b> Source for RegistrySource<'a, 'b> {
     }
 
     fn download(&mut self, packages: &[PackageId]) -> CargoResult<()> {
-        let config = try!(self.config());
-        let url = try!(config.dl.to_url().map_err(internal));
+        let url = if self.source_id.is_local_registry() {
+            None
+        } else {
+            let config = try!(self.config());
+            Some(try!(config.dl.to_url().map_err(internal)))
+        };
         for package in packages.iter() {
             if self.source_id != *package.source_id() { continue }
 
-            let mut url = url.clone();
-            url.path_mut().unwrap().push(package.name().to_string());
-            url.path_mut().unwrap().push(package.version().to_string());
-            url.path_mut().unwrap().push("download".to_string());
-            let path = try!(self.download_package(package, &url).chain_error(|| {
-                internal(format!("Failed to download package `{}` from {}",
-                                 package, url))
-            }));
+            let path = match url {
+                Some(ref url) => {
+                    let mut url = url.clone();
+                    url.path_mut().unwrap().push(package.name().to_string());
+                    url.path_mut().unwrap().push(package.version().to_string());
+                    url.path_mut().unwrap().push("download".to_string());
+                    try!(self.download_package(package, &url).chain_error(|| {
+                        internal(format!("Failed to download package `{}` \
+                                          from {}", package, url))
+                    }))
+                }
+                None => try!(self.local_package(package)),
+            };
             let path = try!(self.unpack_package(package, path).chain_error(|| {
                 internal(format!("Failed to unpack package `{}`", package))
             }));
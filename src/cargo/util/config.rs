@@ -27,6 +27,8 @@ pub struct Config<'a> {
     values: RefCell<HashMap<String, ConfigValue>>,
     values_loaded: Cell<bool>,
     cwd: PathBuf,
+    frozen: Cell<bool>,
+    offline: Cell<bool>,
 }
 
 impl<'a> Config<'a> {
@@ -47,6 +49,8 @@ impl<'a> Config<'a> {
             cwd: cwd,
             values: RefCell::new(HashMap::new()),
             values_loaded: Cell::new(false),
+            frozen: Cell::new(false),
+            offline: Cell::new(false),
         })
     }
 
@@ -92,6 +96,24 @@ impl<'a> Config<'a> {
 
     pub fn cwd(&self) -> &Path { &self.cwd }
 
+    /// Forbid both network access and changes to `Cargo.lock`, see
+    /// `network_allowed` and `lock_update_allowed`.
+    pub fn set_frozen(&self, frozen: bool) { self.frozen.set(frozen) }
+
+    /// Forbid network access, see `network_allowed`.
+    pub fn set_offline(&self, offline: bool) { self.offline.set(offline) }
+
+    /// Whether sources may use the network to update themselves or to
+    /// download packages. If not, they have to make do with what was
+    /// previously fetched into the home directory, or fail.
+    pub fn network_allowed(&self) -> bool {
+        !self.frozen.get() && !self.offline.get()
+    }
+
+    /// Whether `Cargo.lock` may be written if the resolved graph differs from
+    /// the one it records.
+    pub fn lock_update_allowed(&self) -> bool { !self.frozen.get() }
+
     pub fn get(&self, key: &str) -> CargoResult<Option<ConfigValue>> {
         let vals = try!(self.values());
         let mut parts = key.split('.').enumerate();
@@ -132,6 +154,17 @@ impl<'a> Config<'a> {
         }
     }
 
+    /// Looks up a string naming a path. A relative path is taken relative to
+    /// the directory containing the `.cargo` folder it was configured in.
+    pub fn get_path(&self, key: &str) -> CargoResult<Option<PathBuf>> {
+        match try!(self.get_string(key)) {
+            Some((s, path)) => {
+                Ok(Some(path.parent().unwrap().parent().unwrap().join(&s)))
+            }
+            None => Ok(None),
+        }
+    }
+
     pub fn get_table(&self, key: &str)
                     -> CargoResult<Option<(HashMap<String, CV>, PathBuf)>> {
         match try!(self.get(key)) {
@@ -0,0 +1,220 @@
+//! A minimal line based diff, producing output in the unified format of
+//! `diff -u`.
+
+use std::cmp;
+use std::iter;
+
+const CONTEXT: usize = 3;
+
+#[derive(PartialEq, Debug)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Computes a shortest edit script turning `old` into `new`, based on their
+/// longest common subsequence.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    // lcs[i][j] is the length of the longest common subsequence of
+    // old[i..] and new[j..].
+    let mut lcs = (0..old.len() + 1).map(|_| {
+        iter::repeat(0).take(new.len() + 1).collect::<Vec<usize>>()
+    }).collect::<Vec<_>>();
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut ret = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ret.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() ||
+                                    lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ret.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            ret.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+    ret
+}
+
+/// Splits `s` into lines, keeping the newline ending each of them so that
+/// a change of the newline at the end of the file is a change of its last
+/// line.
+fn split_lines(s: &str) -> Vec<&str> {
+    let mut ret = Vec::new();
+    let mut rest = s;
+    while !rest.is_empty() {
+        let end = rest.find('\n').map(|i| i + 1).unwrap_or(rest.len());
+        ret.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    ret
+}
+
+/// Appends `line` to a hunk, marking it like `diff` does if it isn't ended
+/// by a newline.
+fn push_line(body: &mut String, prefix: char, line: &str) {
+    body.push(prefix);
+    body.push_str(line);
+    if !line.ends_with("\n") {
+        body.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// Returns the differences between `old` and `new` in unified format, or
+/// `None` if both are equal. A missing old file (`None`) always differs,
+/// even from empty new contents.
+pub fn unified_diff(old: Option<&str>, new: &str,
+                    old_name: &str, new_name: &str) -> Option<String> {
+    let old_lines = split_lines(old.unwrap_or(""));
+    let new_lines = split_lines(new);
+    let lines = diff_lines(&old_lines, &new_lines);
+
+    let changes = lines.iter().enumerate().filter(|&(_, l)| {
+        match *l { Line::Same(..) => false, _ => true }
+    }).map(|(idx, _)| idx).collect::<Vec<_>>();
+    let mut ret = format!("--- {}\n+++ {}\n", old_name, new_name);
+    if changes.is_empty() {
+        return if old.is_none() { Some(ret) } else { None }
+    }
+
+    // Group changes close enough to each other to share their context.
+    let mut hunks = Vec::new();
+    let mut start = changes[0];
+    let mut end = changes[0];
+    for &idx in changes[1..].iter() {
+        if idx - end > 2 * CONTEXT {
+            hunks.push((start, end));
+            start = idx;
+        }
+        end = idx;
+    }
+    hunks.push((start, end));
+
+    for (start, end) in hunks.into_iter() {
+        let start = start - cmp::min(start, CONTEXT);
+        let end = cmp::min(end + CONTEXT + 1, lines.len());
+
+        // Line numbers in the old and new file where the hunk starts.
+        let (mut old_pos, mut new_pos) = (0, 0);
+        for l in lines[..start].iter() {
+            match *l {
+                Line::Same(..) => { old_pos += 1; new_pos += 1; }
+                Line::Removed(..) => old_pos += 1,
+                Line::Added(..) => new_pos += 1,
+            }
+        }
+
+        let mut body = String::new();
+        let (mut old_len, mut new_len) = (0, 0);
+        for l in lines[start..end].iter() {
+            match *l {
+                Line::Same(s) => {
+                    old_len += 1;
+                    new_len += 1;
+                    push_line(&mut body, ' ', s);
+                }
+                Line::Removed(s) => {
+                    old_len += 1;
+                    push_line(&mut body, '-', s);
+                }
+                Line::Added(s) => {
+                    new_len += 1;
+                    push_line(&mut body, '+', s);
+                }
+            }
+        }
+
+        // Empty ranges refer to the line before them.
+        let old_start = if old_len == 0 { old_pos } else { old_pos + 1 };
+        let new_start = if new_len == 0 { new_pos } else { new_pos + 1 };
+        ret.push_str(&format!("@@ -{},{} +{},{} @@\n", old_start, old_len,
+                              new_start, new_len));
+        ret.push_str(&body);
+    }
+    Some(ret)
+}
+
+#[test]
+fn test_unified_diff() {
+    assert_eq!(unified_diff(Some("a\nb\n"), "a\nb\n", "old", "new"), None);
+
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+    let new = "1\n2\n3\nfour\n5\n6\n7\n8\n9\n10\n11\n12\n13\n";
+    assert_eq!(unified_diff(Some(old), new, "a/x", "b/x").unwrap(), "\
+--- a/x
++++ b/x
+@@ -1,7 +1,7 @@
+ 1
+ 2
+ 3
+-4
++four
+ 5
+ 6
+ 7
+@@ -10,3 +10,4 @@
+ 10
+ 11
+ 12
++13
+");
+
+    assert_eq!(unified_diff(Some(""), "a\n", "a/x", "b/x").unwrap(), "\
+--- a/x
++++ b/x
+@@ -0,0 +1,1 @@
++a
+");
+}
+
+#[test]
+fn test_unified_diff_trailing_newline() {
+    assert_eq!(unified_diff(Some("a\nb"), "a\nb\n", "a/x", "b/x").unwrap(), "\
+--- a/x
++++ b/x
+@@ -1,2 +1,2 @@
+ a
+-b
+\\ No newline at end of file
++b
+");
+
+    assert_eq!(unified_diff(Some("a\n"), "a", "a/x", "b/x").unwrap(), "\
+--- a/x
++++ b/x
+@@ -1,1 +1,1 @@
+-a
++a
+\\ No newline at end of file
+");
+}
+
+#[test]
+fn test_unified_diff_missing_file() {
+    assert_eq!(unified_diff(None, "a\n", "/dev/null", "b/x").unwrap(), "\
+--- /dev/null
++++ b/x
+@@ -0,0 +1,1 @@
++a
+");
+
+    assert_eq!(unified_diff(None, "", "/dev/null", "b/x").unwrap(), "\
+--- /dev/null
++++ b/x
+");
+    assert_eq!(unified_diff(Some(""), "", "a/x", "b/x"), None);
+}
@@ -7,6 +7,7 @@ pub use self::errors::{Human, caused_human};
 pub use self::paths::{join_paths, path2bytes, bytes2path, dylib_path};
 pub use self::paths::{normalize_path, lose_the_slash};
 pub use self::lev_distance::{lev_distance};
+pub use self::diff::unified_diff;
 pub use self::hex::{to_hex, short_hash};
 pub use self::dependency_queue::{DependencyQueue, Fresh, Dirty, Freshness};
 pub use self::dependency_queue::Dependency;
@@ -17,6 +18,7 @@ pub use self::vcs::{GitRepo, HgRepo};
 pub use self::sha256::Sha256;
 
 pub mod config;
+pub mod diff;
 pub mod errors;
 pub mod graph;
 pub mod hex;
@@ -13,8 +13,10 @@ use rustc_serialize::{Decodable, Decoder};
 
 use core::SourceId;
 use core::{Summary, Manifest, Target, Dependency, PackageId, GitReference};
+use core::PackageIdSpec;
 use core::dependency::Kind;
 use core::manifest::{LibKind, Profile, ManifestMetadata};
+use core::manifest::{CustomProfile, ProfileOverride, Profiles};
 use core::package_id::Metadata;
 use util::{CargoResult, human, ToUrl, ToSemver, ChainError, Config};
 
@@ -206,7 +208,7 @@ pub struct DetailedTomlDependency {
 pub struct TomlManifest {
     package: Option<Box<TomlProject>>,
     project: Option<Box<TomlProject>>,
-    profile: Option<TomlProfiles>,
+    profile: Option<HashMap<String, TomlProfile>>,
     lib: Option<ManyOrOne<TomlLibTarget>>,
     bin: Option<Vec<TomlBinTarget>>,
     example: Option<Vec<TomlExampleTarget>>,
@@ -217,9 +219,16 @@ pub struct TomlManifest {
     build_dependencies: Option<HashMap<String, TomlDependency>>,
     features: Option<HashMap<String, Vec<String>>>,
     target: Option<HashMap<String, TomlPlatform>>,
+    replace: Option<HashMap<String, TomlDependency>>,
+    workspace: Option<TomlWorkspace>,
 }
 
 #[derive(RustcDecodable, Clone, Default)]
+pub struct TomlWorkspace {
+    members: Option<Vec<String>>,
+}
+
+#[derive(Clone, Default)]
 pub struct TomlProfiles {
     test: Option<TomlProfile>,
     doc: Option<TomlProfile>,
@@ -236,6 +245,20 @@ pub struct TomlProfile {
     codegen_units: Option<u32>,
     debug: Option<bool>,
     rpath: Option<bool>,
+    inherits: Option<String>,
+    overrides: Option<HashMap<String, TomlProfile>>,
+}
+
+impl TomlProfile {
+    fn to_override(&self) -> ProfileOverride {
+        ProfileOverride {
+            opt_level: self.opt_level,
+            lto: self.lto,
+            codegen_units: self.codegen_units,
+            debug: self.debug,
+            rpath: self.rpath,
+        }
+    }
 }
 
 #[derive(RustcDecodable)]
@@ -450,7 +473,7 @@ impl TomlManifest {
         let new_build = project.build.as_ref().map(PathBuf::new);
 
         // Get targets
-        let profiles = self.profile.clone().unwrap_or(Default::default());
+        let (profiles, custom_profiles) = try!(self.profiles());
         let targets = normalize(&lib,
                                 &bins,
                                 new_build,
@@ -494,6 +517,18 @@ impl TomlManifest {
             }
         }
 
+        let replace = try!(self.replacements(source_id, &mut nested_paths));
+
+        // Members of a workspace are nested in the source of its root, just
+        // like path dependencies, so they all share one source id.
+        let workspace = self.workspace.as_ref().map(|ws| {
+            ws.members.as_ref().map(|m| m.as_slice()).unwrap_or(&[]).iter()
+              .map(|member| PathBuf::new(member)).collect::<Vec<_>>()
+        });
+        if let Some(ref members) = workspace {
+            nested_paths.extend(members.iter().cloned());
+        }
+
         let exclude = project.exclude.clone().unwrap_or(Vec::new());
         let include = project.include.clone().unwrap_or(Vec::new());
 
@@ -518,7 +553,10 @@ impl TomlManifest {
                                          exclude,
                                          include,
                                          project.links.clone(),
-                                         metadata);
+                                         metadata,
+                                         replace,
+                                         workspace,
+                                         custom_profiles);
         if used_deprecated_lib {
             manifest.add_warning(format!("the [[lib]] section has been \
                                           deprecated in favor of [lib]"));
@@ -530,6 +568,127 @@ impl TomlManifest {
 
         Ok((manifest, nested_paths))
     }
+
+    /// Splits the `[profile]` section into the built-in profiles, which are
+    /// merged into the profiles of the targets, and the custom profiles and
+    /// per-package overrides.
+    fn profiles(&self) -> CargoResult<(TomlProfiles, Profiles)> {
+        let empty = HashMap::new();
+        let toml = self.profile.as_ref().unwrap_or(&empty);
+        let builtin = TomlProfiles {
+            test: toml.get("test").cloned(),
+            doc: toml.get("doc").cloned(),
+            bench: toml.get("bench").cloned(),
+            dev: toml.get("dev").cloned(),
+            release: toml.get("release").cloned(),
+        };
+
+        let mut custom = HashMap::new();
+        let mut overrides = HashMap::new();
+        for (name, profile) in toml.iter() {
+            let env = match &name[..] {
+                "dev" | "release" | "test" | "bench" | "doc" => {
+                    if profile.inherits.is_some() {
+                        return Err(human(format!("the built-in profile `{}` \
+                                                  cannot inherit from another \
+                                                  profile", name)))
+                    }
+                    if name == "dev" {"compile".to_string()} else {name.clone()}
+                }
+                // The artifacts of custom profiles are placed in a directory
+                // named after them, which must not clash with the directories
+                // Cargo already uses in the target directory. `release` and
+                // `doc` are taken by the built-in profiles above.
+                "debug" | "package" | "debian" => {
+                    return Err(human(format!("profile name `{}` is reserved",
+                                             name)))
+                }
+                _ => {
+                    let env = match profile.inherits.as_ref().map(|s| &s[..]) {
+                        Some("dev") => "compile",
+                        Some("release") => "release",
+                        _ => return Err(human(format!("profile `{}` must \
+                                                       inherit from `dev` or \
+                                                       `release`", name))),
+                    };
+                    custom.insert(name.clone(), CustomProfile {
+                        env: env.to_string(),
+                        settings: profile.to_override(),
+                    });
+                    name.clone()
+                }
+            };
+            for (pkg, o) in profile.overrides.iter().flat_map(|o| o.iter()) {
+                if o.inherits.is_some() || o.overrides.is_some() {
+                    return Err(human(format!("the override of `{}` in profile \
+                                              `{}` may only change settings",
+                                             pkg, name)))
+                }
+                overrides.insert((env.clone(), pkg.clone()), o.to_override());
+            }
+        }
+        Ok((builtin, Profiles::new(custom, overrides)))
+    }
+
+    /// Parses the `[replace]` table, which maps package id specs to the path
+    /// or git repository the matching packages should be taken from instead.
+    fn replacements(&self, source_id: &SourceId,
+                    nested_paths: &mut Vec<PathBuf>)
+                    -> CargoResult<Vec<(PackageIdSpec, Dependency)>> {
+        let mut replace = Vec::new();
+        let table = match self.replace {
+            Some(ref table) => table,
+            None => return Ok(replace),
+        };
+        for (spec, replacement) in table.iter() {
+            let pkgid_spec = try!(PackageIdSpec::parse(spec).chain_error(|| {
+                human(format!("invalid package id spec `{}` in [replace]", spec))
+            }));
+            let details = match *replacement {
+                TomlDependency::Detailed(ref details) => details,
+                TomlDependency::Simple(..) => {
+                    return Err(human(format!("the replacement for `{}` must \
+                                              be a table specifying a `path` \
+                                              or `git` key", spec)))
+                }
+            };
+            if details.version.is_some() {
+                return Err(human(format!("replacements cannot specify a \
+                                          version requirement, but found one \
+                                          for `{}`", spec)))
+            }
+            let source_id = match (details.git.as_ref(), details.path.as_ref()) {
+                (Some(git), None) => {
+                    let loc = try!(git.to_url().map_err(human));
+                    SourceId::for_git(&loc, details.git_reference())
+                }
+                // Just like path dependencies, a replacement from a path is
+                // a package nested in the source of this one.
+                (None, Some(path)) => {
+                    nested_paths.push(PathBuf::new(path));
+                    source_id.clone()
+                }
+                _ => {
+                    return Err(human(format!("the replacement for `{}` must \
+                                              specify exactly one of `path` \
+                                              or `git`", spec)))
+                }
+            };
+            let dep = try!(Dependency::parse(pkgid_spec.name(), None,
+                                             &source_id));
+            replace.push((pkgid_spec, dep));
+        }
+        Ok(replace)
+    }
+}
+
+impl DetailedTomlDependency {
+    fn git_reference(&self) -> GitReference {
+        self.branch.clone().map(GitReference::Branch)
+            .or_else(|| self.tag.clone().map(GitReference::Tag))
+            .or_else(|| self.rev.clone().map(GitReference::Rev))
+            .unwrap_or_else(|| GitReference::Branch("master".to_string()))
+    }
 }
 
 fn process_dependencies<F>(cx: &mut Context,
@@ -550,10 +709,7 @@ fn process_dependencies<F>(cx: &mut Context,
             }
             TomlDependency::Detailed(ref details) => details.clone(),
         };
-        let reference = details.branch.clone().map(GitReference::Branch)
-            .or_else(|| details.tag.clone().map(GitReference::Tag))
-            .or_else(|| details.rev.clone().map(GitReference::Rev))
-            .unwrap_or_else(|| GitReference::Branch("master".to_string()));
+        let reference = details.git_reference();
 
         let new_source_id = match details.git {
             Some(ref git) => {
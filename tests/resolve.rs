@@ -9,7 +9,7 @@ use cargo::core::source::{SourceId, GitReference};
 use cargo::core::dependency::Kind::Development;
 use cargo::core::{Dependency, PackageId, Summary, Registry};
 use cargo::util::{CargoResult, ToUrl};
-use cargo::core::resolver::{self, Method};
+use cargo::core::resolver::{self, Method, ResolveOptions};
 
 fn resolve<R: Registry>(pkg: PackageId, deps: Vec<Dependency>,
                         registry: &mut R)
@@ -21,6 +21,17 @@ fn resolve<R: Registry>(pkg: PackageId, deps: Vec<Dependency>,
     }).collect())
 }
 
+fn resolve_minimal<R: Registry>(pkg: PackageId, deps: Vec<Dependency>,
+                                registry: &mut R)
+                                -> CargoResult<Vec<PackageId>> {
+    let summary = Summary::new(pkg, deps, HashMap::new()).unwrap();
+    let opts = ResolveOptions { minimal_versions: true };
+    let resolve = try!(resolver::resolve_with_options(&summary,
+                                                      Method::Everything,
+                                                      opts, registry));
+    Ok(resolve.iter().map(|p| p.clone()).collect())
+}
+
 trait ToDep {
     fn to_dep(self) -> Dependency;
 }
@@ -218,6 +229,23 @@ fn resolving_with_many_versions() {
                                        ("foo", "1.0.2")])));
 }
 
+#[test]
+fn resolving_with_minimal_versions() {
+    let mut reg = registry(vec!(
+        pkg!(("foo", "1.0.1")),
+        pkg!(("foo", "1.0.2")),
+        pkg!(("bar", "1.0.0") => [dep_req("foo", "^1.0.0")]),
+        pkg!(("bar", "1.1.0") => [dep_req("foo", "^1.0.2")]),
+    ));
+
+    let res = resolve_minimal(pkg_id("root"), vec![dep_req("bar", "^1.0.0")],
+                              &mut reg).unwrap();
+
+    assert_that(&res, contains(names(&[("root", "1.0.0"),
+                                       ("bar", "1.0.0"),
+                                       ("foo", "1.0.1")])));
+}
+
 #[test]
 fn resolving_with_specific_version() {
     let mut reg = registry(vec!(
@@ -246,6 +274,41 @@ fn resolving_incompat_versions() {
     ], &mut reg).is_err());
 }
 
+#[test]
+fn resolving_incompat_versions_explains_conflict() {
+    let mut reg = registry(vec!(
+        pkg!(("foo", "1.0.1")),
+        pkg!(("foo", "1.0.2")),
+        pkg!(("bar", "1.0.0") => [dep_req("foo", "=1.0.2")]),
+        pkg!(("bar", "1.0.1") => [dep_req("foo", "=1.0.2")]),
+    ));
+
+    let res = resolve(pkg_id("root"), vec![
+        dep_req("foo", "=1.0.1"),
+        dep("bar"),
+    ], &mut reg);
+
+    assert_eq!(res.err().unwrap().to_string(), "\
+failed to select a version for `foo` (required by `bar`):
+all possible versions conflict with previously selected versions of `foo`
+
+  version 1.0.1 was selected through:
+    root v1.0.0
+    └── foo = 1.0.1 -> foo v1.0.1
+
+  but `foo` is also required through:
+    root v1.0.0
+    └── bar ^1.0.0 -> bar v1.0.0
+        └── foo = 1.0.2
+
+  possible versions to select: 1.0.2
+
+consider loosening one of these requirements:
+  `foo = 1.0.1` of `root v1.0.0`
+  `foo = 1.0.2` of `bar v1.0.0`\
+");
+}
+
 #[test]
 fn resolving_backtrack() {
     let mut reg = registry(vec!(
@@ -349,3 +412,100 @@ fn resolving_cycle() {
         dep_req("foo", "1"),
     ], &mut reg);
 }
+
+fn named_pkg(name: &str, vers: &str, deps: Vec<Dependency>) -> Summary {
+    let pkgid = PackageId::new(name, vers, &registry_loc()).unwrap();
+    Summary::new(pkgid, deps, HashMap::new()).unwrap()
+}
+
+#[test]
+fn resolving_long_chain() {
+    let n = 2000;
+    let mut reg = registry((0..n).map(|i| {
+        let deps = if i + 1 < n {
+            vec![dep_req(&format!("p{}", i + 1), "*")]
+        } else {
+            Vec::new()
+        };
+        named_pkg(&format!("p{}", i), "1.0.0", deps)
+    }).collect());
+
+    let res = resolve(pkg_id("root"), vec![
+        dep_req("p0", "*"),
+    ], &mut reg).unwrap();
+
+    assert_eq!(res.len(), n + 1);
+}
+
+// Counts the queries reaching the registry. The resolver caches the
+// candidates of every dependency, so this is the number of distinct
+// dependencies it had to look at.
+struct CountingRegistry {
+    summaries: Vec<Summary>,
+    queries: usize,
+}
+
+impl Registry for CountingRegistry {
+    fn query(&mut self, dep: &Dependency) -> CargoResult<Vec<Summary>> {
+        self.queries += 1;
+        self.summaries.query(dep)
+    }
+}
+
+#[test]
+fn resolving_backjumps_over_unrelated_choices() {
+    let n = 50;
+    let mut pkgs = vec![named_pkg("w", "1.0.0", Vec::new())];
+    let mut deps = Vec::new();
+    for i in 0..n {
+        for vers in ["1.0.0", "1.1.0", "1.2.0"].iter() {
+            pkgs.push(named_pkg(&format!("x{}", i), vers,
+                                vec![dep_req("w", "*")]));
+        }
+        deps.push(dep_req(&format!("x{}", i), "*"));
+    }
+    for vers in ["1.0.0", "1.1.0", "1.2.0"].iter() {
+        pkgs.push(named_pkg("z", vers, vec![dep_req("x0", "=1.0.0")]));
+    }
+    deps.push(dep_req("z", "*"));
+    let mut reg = CountingRegistry { summaries: registry(pkgs), queries: 0 };
+
+    let res = resolve(pkg_id("root"), deps, &mut reg).unwrap();
+
+    assert_that(&res, contains(names(&[("x0", "1.0.0"),
+                                       ("x1", "1.2.0"),
+                                       ("x49", "1.2.0"),
+                                       ("z", "1.2.0")])));
+    // The dependencies of the root, of every `x` at 1.2.0, of the versions of
+    // `x0` and of the versions of `z`. Retrying older versions of `x1` to
+    // `x49` would query their dependencies as well.
+    assert!(reg.queries <= 2 * n + 8,
+            "{} queries for {} packages", reg.queries, n);
+}
+
+#[test]
+fn resolving_unresolvable_after_many_choices() {
+    let n = 50;
+    let mut pkgs = Vec::new();
+    let mut deps = Vec::new();
+    for i in 0..n {
+        for vers in ["1.0.0", "2.0.0", "3.0.0"].iter() {
+            pkgs.push(named_pkg(&format!("x{}", i), vers, Vec::new()));
+        }
+        deps.push(dep_req(&format!("x{}", i), "*"));
+    }
+    for vers in ["1.0.0", "1.1.0", "1.2.0", "1.3.0"].iter() {
+        pkgs.push(named_pkg("y", vers, vec![dep_req("missing", "*")]));
+    }
+    deps.push(dep_req("y", "*"));
+    let mut reg = registry(pkgs);
+
+    let res = resolve(pkg_id("root"), deps, &mut reg);
+
+    assert_eq!(res.err().unwrap().to_string(), "\
+no matching package named `missing` found (required by `y`)
+location searched: registry http://example.com/
+version required: *\
+");
+}
+
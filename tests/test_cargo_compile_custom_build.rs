@@ -3,7 +3,7 @@ use std::fs::{self, File};
 use std::io::prelude::*;
 
 use support::{project, execs};
-use support::{COMPILING, RUNNING, DOCTEST};
+use support::{COMPILING, RUNNING, DOCTEST, FRESH};
 use support::paths::CargoPathExt;
 use hamcrest::{assert_that};
 
@@ -1120,3 +1120,185 @@ test!(build_script_with_lto {
     assert_that(build.cargo_process("build"),
                 execs().with_status(0));
 });
+
+test!(rerun_if_changed_ignores_other_files {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("input.txt", "")
+        .file("build.rs", r#"
+            fn main() {
+                println!("cargo:rerun-if-changed=input.txt");
+            }
+        "#);
+
+    assert_that(p.cargo_process("build").arg("-v"),
+                execs().with_status(0));
+    p.root().move_into_the_past().unwrap();
+
+    File::create(&p.root().join("some-new-file")).unwrap();
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stdout(format!("\
+{fresh} foo v0.5.0 (file://[..])
+", fresh = FRESH).as_slice()));
+
+    File::create(&p.root().join("input.txt")).unwrap();
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stdout(format!("\
+{compiling} foo v0.5.0 (file://[..])
+{running} `[..]build-script-build[..]`
+{running} `rustc [..] --crate-name foo [..]`
+", compiling = COMPILING, running = RUNNING).as_slice()));
+});
+
+test!(rerun_if_env_changed {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            fn main() {
+                println!("cargo:rerun-if-env-changed=FOO_NATIVE_DIR");
+            }
+        "#);
+
+    assert_that(p.cargo_process("build").env("FOO_NATIVE_DIR", "a"),
+                execs().with_status(0));
+    assert_that(p.cargo("build").arg("-v").env("FOO_NATIVE_DIR", "a"),
+                execs().with_status(0)
+                       .with_stdout(format!("\
+{fresh} foo v0.5.0 (file://[..])
+", fresh = FRESH).as_slice()));
+    assert_that(p.cargo("build").arg("-v").env("FOO_NATIVE_DIR", "b"),
+                execs().with_status(0)
+                       .with_stdout(format!("\
+{compiling} foo v0.5.0 (file://[..])
+{running} `[..]build-script-build[..]`
+{running} `rustc [..] --crate-name foo [..]`
+", compiling = COMPILING, running = RUNNING).as_slice()));
+});
+
+test!(build_script_cfg_env_and_warnings {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/main.rs", r#"
+            #[cfg(has_foo)]
+            fn foo() -> &'static str { env!("FOO_VALUE") }
+
+            fn main() { assert_eq!(foo(), "bar=baz"); }
+        "#)
+        .file("build.rs", r#"
+            fn main() {
+                println!("cargo:rustc-cfg=has_foo");
+                println!("cargo:rustc-env=FOO_VALUE=bar=baz");
+                println!("cargo:rustc-link-search=native=foo");
+                println!("cargo:warning=foo is not installed");
+            }
+        "#);
+
+    assert_that(p.cargo_process("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stdout(format!("\
+{compiling} foo v0.5.0 (file://[..])
+{running} `rustc build.rs [..]`
+{running} `[..]build-script-build[..]`
+{running} `rustc [..] --crate-name foo [..] -L native=foo --cfg has_foo`
+", compiling = COMPILING, running = RUNNING).as_slice())
+                       .with_stderr("\
+warning: foo is not installed (build script of `foo v0.5.0 (file://[..])`)
+"));
+
+    assert_that(p.process(&p.bin("foo")),
+                execs().with_status(0));
+});
+
+test!(build_script_warnings_shown_when_build_fails {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/main.rs", "not valid rust code")
+        .file("build.rs", r#"
+            fn main() {
+                println!("cargo:warning=foo is not installed");
+            }
+        "#);
+
+    let output = match p.cargo_process("build").exec_with_output() {
+        Ok(..) => panic!("expected the build to fail"),
+        Err(e) => e.output.unwrap(),
+    };
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("warning: foo is not installed (build script \
+                                of `foo v0.5.0 (file://"), "{}", stderr);
+});
+
+test!(overrides_with_structured_keys {
+    let (_, target) = ::cargo::ops::rustc_version().unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.a]
+            path = "a"
+        "#)
+        .file("src/lib.rs", r#"
+            extern crate a;
+            pub fn foo() -> &'static str { a::foo() }
+        "#)
+        .file(".cargo/config", format!(r#"
+            [target.{}.foo]
+            rustc-link-search = ["native=foo"]
+            rustc-cfg = ["has_foo"]
+            rustc-env = {{ FOO_VERSION = "1.0" }}
+        "#, target).as_slice())
+        .file("a/Cargo.toml", r#"
+            [project]
+            name = "a"
+            version = "0.5.0"
+            authors = []
+            links = "foo"
+            build = "build.rs"
+        "#)
+        .file("a/src/lib.rs", r#"
+            #[cfg(has_foo)]
+            pub fn foo() -> &'static str { env!("FOO_VERSION") }
+        "#)
+        .file("a/build.rs", "not valid rust code");
+
+    assert_that(p.cargo_process("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stdout(format!("\
+{compiling} a v0.5.0 (file://[..])
+{running} `rustc [..] --crate-name a [..] -L native=foo --cfg has_foo`
+{compiling} foo v0.5.0 (file://[..])
+{running} `rustc [..] --crate-name foo [..] -L native=foo`
+", compiling = COMPILING, running = RUNNING).as_slice()));
+});
@@ -1676,3 +1676,61 @@ test!(dont_require_submodules_are_checked_out {
     assert_that(git1.cargo("build").arg("-v").cwd(&dst),
                 execs().with_status(0));
 });
+
+test!(force_pushed_locked_revision {
+    let bar = git_repo("bar", |project| {
+        project.file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn bar() -> i32 { 1 }")
+    }).unwrap();
+    let repo = git2::Repository::open(&bar.root()).unwrap();
+    let first = repo.revparse_single("HEAD").unwrap().id();
+    File::create(&bar.root().join("src/lib.rs")).unwrap()
+         .write_all(b"pub fn bar() -> i32 { 2 }").unwrap();
+    add(&repo);
+    commit(&repo);
+
+    let foo = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [project]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/main.rs", r#"
+            extern crate bar;
+
+            fn main() { bar::bar(); }
+        "#);
+    assert_that(foo.cargo_process("build"), execs().with_status(0));
+
+    // Rewrite the locked commit upstream, as a force-push would.
+    let obj = repo.find_object(first, None).unwrap();
+    repo.reset(&obj, git2::ResetType::Hard, None, None, None).unwrap();
+    File::create(&bar.root().join("src/lib.rs")).unwrap()
+         .write_all(b"pub fn bar() -> i32 { 3 }").unwrap();
+    add(&repo);
+    commit(&repo);
+
+    // Building from the lockfile on a machine without the locked revision
+    // must not silently pick up the rewritten history.
+    fs::remove_dir_all(&paths::home().join(".cargo/git")).unwrap();
+    assert_that(foo.cargo("build"),
+                execs().with_status(101).with_stderr(format!("\
+Unable to update {bar}#[..]
+
+Caused by:
+  revision [..] of git repository `{bar}` locked in Cargo.lock is no longer \
+part of its history, run `cargo update` to accept the rewritten history
+", bar = bar.url())));
+
+    assert_that(foo.cargo("update"), execs().with_status(0));
+    assert_that(foo.cargo("build"), execs().with_status(0));
+});
@@ -674,3 +674,31 @@ test!(plugin_build_script_right_arch {
 {running} `rustc src[..]lib.rs [..]`
 ", compiling = COMPILING, running = RUNNING)));
 });
+
+test!(cross_rustflags_for_host_and_target {
+    if disabled() { return }
+
+    let target = alternate();
+    let (_, host) = rustc_version().unwrap();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+            build = 'build.rs'
+        "#)
+        .file("build.rs", "#[cfg(host)] fn main() {}")
+        .file("src/main.rs", "#[cfg(target)] fn main() {}")
+        .file(".cargo/config", format!(r#"
+            [target.{}]
+            rustflags = "--cfg host"
+
+            [target.{}]
+            rustflags = "--cfg target"
+        "#, host, target).as_slice());
+
+    assert_that(p.cargo_process("build").arg("--target").arg(&target)
+                 .env_remove("RUSTFLAGS"),
+                execs().with_status(0));
+});
@@ -0,0 +1,218 @@
+use std::fs::File;
+use std::io::prelude::*;
+
+use support::{project, execs, cargo_dir};
+use support::{COMPILING, UPDATING};
+use support::registry as r;
+
+use hamcrest::assert_that;
+
+fn setup() {
+    r::init();
+}
+
+test!(frozen_without_lockfile {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+    p.build();
+
+    assert_that(p.process(&cargo_dir().join("cargo")).arg("--frozen")
+                 .arg("build"),
+                execs().with_status(101).with_stderr("\
+the lock file [..]Cargo.lock needs to be updated but --frozen was passed to \
+prevent this
+"));
+});
+
+test!(frozen_with_outdated_lockfile {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0));
+    assert_that(p.process(&cargo_dir().join("cargo")).arg("--frozen")
+                 .arg("build"),
+                execs().with_status(0));
+
+    File::create(&p.root().join("Cargo.toml")).unwrap().write_all(br#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies.bar]
+        path = "bar"
+    "#).unwrap();
+
+    assert_that(p.process(&cargo_dir().join("cargo")).arg("--frozen")
+                 .arg("build"),
+                execs().with_status(101).with_stderr("\
+the lock file [..]Cargo.lock needs to be updated but --frozen was passed to \
+prevent this
+"));
+});
+
+test!(frozen_after_subcommand {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+    p.build();
+
+    assert_that(p.process(&cargo_dir().join("cargo")).arg("build")
+                 .arg("--frozen"),
+                execs().with_status(101).with_stderr("\
+the lock file [..]Cargo.lock needs to be updated but --frozen was passed to \
+prevent this
+"));
+    assert_that(p.process(&cargo_dir().join("cargo")).arg("build").arg("-v")
+                 .arg("--offline"),
+                execs().with_status(0));
+});
+
+test!(offline_without_index {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+
+    assert_that(p.process(&cargo_dir().join("cargo")).arg("--offline")
+                 .arg("build"),
+                execs().with_status(101).with_stderr(format!("\
+cannot update registry `{reg}` without network access (--frozen or --offline \
+was passed) as its index was never fetched
+", reg = r::registry())));
+});
+
+test!(offline_without_download {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+
+    assert_that(p.cargo_process("generate-lockfile"),
+                execs().with_status(0));
+    assert_that(p.process(&cargo_dir().join("cargo")).arg("--offline")
+                 .arg("build").arg("-v"),
+                execs().with_status(101).with_stderr("\
+Unable to get packages from source
+
+Caused by:
+  Failed to download package `bar v0.0.1 (registry file://[..])` from [..]
+
+Caused by:
+  cannot download `bar v0.0.1 (registry file://[..])` without network access \
+(--frozen or --offline was passed)
+"));
+});
+
+test!(offline_after_online_build {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0));
+    assert_that(p.cargo("clean"),
+                execs().with_status(0));
+
+    // A newer version is ignored, neither the index nor the lockfile are
+    // updated.
+    r::mock_pkg("bar", "0.0.2", &[]);
+
+    assert_that(p.process(&cargo_dir().join("cargo")).arg("--offline")
+                 .arg("update"),
+                execs().with_status(0).with_stdout(""));
+    assert_that(p.process(&cargo_dir().join("cargo")).arg("--offline")
+                 .arg("build"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} bar v0.0.1 (registry file://[..])
+{compiling} foo v0.0.1 ({dir})
+",
+        compiling = COMPILING,
+        dir = p.url())));
+
+    // Once online again the new version is picked up.
+    assert_that(p.cargo("update"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `{reg}`
+",
+        updating = UPDATING,
+        reg = r::registry())));
+    let mut lock = String::new();
+    File::open(&p.root().join("Cargo.lock")).unwrap()
+         .read_to_string(&mut lock).unwrap();
+    assert!(lock.contains("bar 0.0.2"), "{}", lock);
+});
@@ -0,0 +1,158 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::PathBuf;
+
+use support::{project, execs, cargo_dir, ProjectBuilder};
+use support::{COMPILING, FRESH};
+use support::paths::{self, CargoPathExt};
+use support::registry as r;
+
+use hamcrest::assert_that;
+
+fn setup() {
+    r::init();
+}
+
+// Lays out the packages published to the mock registry as a local registry.
+fn local_registry(pkgs: &[(&str, &str)]) -> PathBuf {
+    let root = paths::root().join("local");
+    for &(name, version) in pkgs.iter() {
+        let file = match name.len() {
+            1 => format!("1/{}", name),
+            2 => format!("2/{}", name),
+            3 => format!("3/{}/{}", &name[..1], name),
+            _ => format!("{}/{}/{}", &name[0..2], &name[2..4], name),
+        };
+        let dst = root.join("index").join(&file);
+        fs::create_dir_all(dst.parent().unwrap()).unwrap();
+        fs::copy(&r::registry_path().join(&file), &dst).unwrap();
+        fs::copy(&r::mock_archive_dst(name, version),
+                 &root.join(&format!("{}-{}.crate", name, version))).unwrap();
+    }
+    root
+}
+
+fn read_lockfile(p: &ProjectBuilder) -> String {
+    let mut lock = String::new();
+    File::open(&p.root().join("Cargo.lock")).unwrap()
+         .read_to_string(&mut lock).unwrap();
+    lock
+}
+
+test!(build_from_local_registry {
+    r::mock_pkg("bar", "0.1.0", &[]);
+    let local = local_registry(&[("bar", "0.1.0")]);
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/main.rs", "
+            extern crate bar;
+            fn main() {}
+        ")
+        .file(".cargo/config", &format!(r#"
+            [registry]
+            local-registry = '{}'
+        "#, local.display()));
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} bar v0.1.0 (local registry file://[..])
+{compiling} foo v0.0.1 ({dir})
+",
+        compiling = COMPILING,
+        dir = p.url())));
+
+    let lock = read_lockfile(&p);
+    assert!(lock.contains("bar 0.1.0 (local-registry+file://"), "{}", lock);
+
+    assert_that(p.process(&cargo_dir().join("cargo")).arg("--frozen")
+                 .arg("build"),
+                execs().with_status(0).with_stdout(format!("\
+{fresh} bar v0.1.0 (local registry file://[..])
+{fresh} foo v0.0.1 ({dir})
+",
+        fresh = FRESH,
+        dir = p.url())));
+});
+
+test!(local_registry_bad_checksum {
+    r::mock_pkg("bar", "0.1.0", &[]);
+    let local = local_registry(&[("bar", "0.1.0")]);
+    File::create(&local.join("bar-0.1.0.crate")).unwrap()
+         .write_all(b"not a tarball").unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", &format!(r#"
+            [registry]
+            local-registry = '{}'
+        "#, local.display()));
+
+    assert_that(p.cargo_process("build").arg("-v"),
+                execs().with_status(101).with_stderr("\
+Unable to get packages from source
+
+Caused by:
+  Failed to verify the checksum of `bar v0.1.0 (local registry file://[..])`
+"));
+});
+
+test!(directory_source_modified_file {
+    r::mock_pkg("bar", "0.1.0", &[]);
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("vendor"),
+                execs().with_status(0));
+    fs::create_dir_all(&p.root().join(".cargo")).unwrap();
+    File::create(&p.root().join(".cargo/config")).unwrap().write_all(br#"
+        [registry]
+        directory = "vendor"
+    "#).unwrap();
+    paths::home().join(".cargo/registry").rm_rf().unwrap();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(0));
+    let lock = read_lockfile(&p);
+    assert!(lock.contains("bar 0.1.0 (directory+file://"), "{}", lock);
+
+    File::create(&p.root().join("vendor/bar-0.1.0/src/lib.rs")).unwrap()
+         .write_all(b"pub fn edited() {}").unwrap();
+
+    assert_that(p.cargo("build").arg("-v"),
+                execs().with_status(101).with_stderr("\
+Unable to get packages from source
+
+Caused by:
+  the listed checksum of `[..]src[..]lib.rs` has changed:
+expected: [..]
+actual:   [..]
+"));
+});
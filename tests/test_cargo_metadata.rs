@@ -0,0 +1,133 @@
+use std::str;
+
+use serialize::json::Json;
+
+use support::{project, execs, ProjectBuilder};
+use support::registry as r;
+
+use hamcrest::assert_that;
+
+fn setup() {
+    r::init();
+}
+
+fn metadata(p: &ProjectBuilder, args: &[&str]) -> Json {
+    let output = p.cargo("metadata").args(args).exec_with_output().unwrap();
+    let output = str::from_utf8(&output.stdout).unwrap();
+    Json::from_str(output).unwrap()
+}
+
+fn strings(json: &Json) -> Vec<&str> {
+    json.as_array().unwrap().iter().map(|s| s.as_string().unwrap()).collect()
+}
+
+test!(whole_graph {
+    r::mock_pkg("baz", "0.1.0", &[]);
+    r::mock_pkg("bar", "0.1.0", &[("baz", "^0.1.0", "normal")]);
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = ["someone"]
+            license = "MIT"
+
+            [dependencies]
+            bar = "0.1.0"
+
+            [build-dependencies]
+            baz = { version = "0.1.0", optional = true }
+
+            [features]
+            default = ["baz"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .file("tests/it.rs", "");
+    p.build();
+
+    let json = metadata(&p, &[]);
+    assert_eq!(json.find("version").unwrap().as_u64(), Some(1));
+
+    let packages = json.find("packages").unwrap().as_array().unwrap();
+    let names = packages.iter().map(|p| {
+        p.find("name").unwrap().as_string().unwrap()
+    }).collect::<Vec<_>>();
+    assert_eq!(names, vec!["bar", "baz", "foo"]);
+
+    let foo = &packages[2];
+    assert_eq!(foo.find("license").unwrap().as_string(), Some("MIT"));
+    assert_eq!(strings(foo.find("authors").unwrap()), vec!["someone"]);
+    assert_eq!(strings(foo.find_path(&["features", "default"]).unwrap()),
+               vec!["baz"]);
+
+    let deps = foo.find("dependencies").unwrap().as_array().unwrap();
+    let baz = deps.iter().find(|d| {
+        d.find("name").unwrap().as_string() == Some("baz")
+    }).unwrap();
+    assert_eq!(baz.find("kind").unwrap().as_string(), Some("build"));
+    assert_eq!(baz.find("req").unwrap().as_string(), Some("^0.1.0"));
+    assert_eq!(baz.find("optional").unwrap().as_boolean(), Some(true));
+    assert!(baz.find("target").unwrap().is_null());
+
+    let targets = foo.find("targets").unwrap().as_array().unwrap();
+    let mut kinds = targets.iter().map(|t| {
+        (t.find("name").unwrap().as_string().unwrap(),
+         strings(t.find("kind").unwrap())[0])
+    }).collect::<Vec<_>>();
+    kinds.sort();
+    assert_eq!(kinds, vec![("foo", "bin"), ("foo", "lib"), ("it", "test")]);
+
+    let resolve = json.find("resolve").unwrap();
+    let root = resolve.find("root").unwrap().as_string().unwrap();
+    assert!(root.starts_with("foo 0.0.1 (path+file://"), "{}", root);
+    let nodes = resolve.find("nodes").unwrap().as_array().unwrap();
+    assert_eq!(nodes.len(), 3);
+    let foo = nodes.iter().find(|n| {
+        n.find("id").unwrap().as_string() == Some(root)
+    }).unwrap();
+    assert_eq!(strings(foo.find("features").unwrap()), vec!["baz", "default"]);
+    let deps = strings(foo.find("dependencies").unwrap());
+    assert_eq!(deps.len(), 2);
+    assert!(deps[0].starts_with("bar 0.1.0 (registry+"), "{:?}", deps);
+    assert!(deps[1].starts_with("baz 0.1.0 (registry+"), "{:?}", deps);
+
+    assert!(!p.root().join("Cargo.lock").exists());
+});
+
+test!(no_deps {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/lib.rs", "");
+    p.build();
+
+    let json = metadata(&p, &["--no-deps"]);
+    let packages = json.find("packages").unwrap().as_array().unwrap();
+    assert_eq!(packages.len(), 1);
+    assert!(json.find("resolve").unwrap().is_null());
+});
+
+test!(unsupported_format_version {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("metadata").arg("--format-version").arg("2"),
+                execs().with_status(101).with_stderr("\
+metadata version 2 not supported, only 1 is currently supported
+"));
+});
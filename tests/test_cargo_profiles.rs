@@ -113,3 +113,121 @@ test!(top_level_overrides_deps {
                     prefix = env::consts::DLL_PREFIX,
                     suffix = env::consts::DLL_SUFFIX).as_slice()));
 });
+
+test!(custom_profile_inherits {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "test"
+            version = "0.0.0"
+            authors = []
+
+            [profile.release]
+            opt-level = 2
+
+            [profile.release-debug]
+            inherits = "release"
+            debug = true
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    assert_that(p.cargo_process("build").arg("-v")
+                 .arg("--profile").arg("release-debug"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} test v0.0.0 ({url})
+{running} `rustc src{sep}main.rs --crate-name test --crate-type bin \
+        -C opt-level=2 \
+        -g [..]\
+        --out-dir {dir}{sep}target{sep}release-debug \
+        --emit=dep-info,link \
+        -L dependency={dir}{sep}target{sep}release-debug \
+        -L dependency={dir}{sep}target{sep}release-debug{sep}deps`
+",
+running = RUNNING, compiling = COMPILING, sep = old_path::SEP,
+dir = p.root().display(),
+url = p.url(),
+)));
+
+    assert_that(p.cargo("build").arg("--profile").arg("missing"),
+                execs().with_status(101).with_stderr("\
+profile `missing` is not defined in the manifest of `test v0.0.0 ([..])`
+"));
+});
+
+test!(custom_profile_must_inherit {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "test"
+            version = "0.0.0"
+            authors = []
+
+            [profile.fast]
+            opt-level = 3
+        "#)
+        .file("src/lib.rs", "");
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr("\
+failed to parse manifest at `[..]`
+
+Caused by:
+  profile `fast` must inherit from `dev` or `release`
+"));
+});
+
+test!(custom_profile_reserved_name {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "test"
+            version = "0.0.0"
+            authors = []
+
+            [profile.package]
+            inherits = "dev"
+        "#)
+        .file("src/lib.rs", "");
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr("\
+failed to parse manifest at `[..]`
+
+Caused by:
+  profile name `package` is reserved
+"));
+});
+
+test!(package_overrides_in_dev {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "test"
+            version = "0.0.0"
+            authors = []
+
+            [dependencies.foo]
+            path = "foo"
+
+            [profile.dev.overrides.foo]
+            opt-level = 3
+        "#)
+        .file("src/lib.rs", "")
+        .file("foo/Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("foo/src/lib.rs", "");
+    assert_that(p.cargo_process("build").arg("-v"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.0 ({url})
+{running} `rustc foo{sep}src{sep}lib.rs --crate-name foo --crate-type lib \
+        -C opt-level=3 \
+        -g [..]`
+{compiling} test v0.0.0 ({url})
+{running} `rustc src{sep}lib.rs --crate-name test --crate-type lib \
+        -g [..]`
+",
+running = RUNNING, compiling = COMPILING, sep = old_path::SEP,
+url = p.url(),
+)));
+});
@@ -171,6 +171,116 @@ Caused by:
 "));
 });
 
+test!(lockfile_records_cksum {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = ">= 0.0.0"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+
+    assert_that(p.cargo_process("generate-lockfile"),
+                execs().with_status(0));
+
+    let mut c = Vec::new();
+    File::open(&r::mock_archive_dst("bar", "0.0.1")).unwrap()
+         .read_to_end(&mut c).unwrap();
+    let mut lock = String::new();
+    File::open(&p.root().join("Cargo.lock")).unwrap()
+         .read_to_string(&mut lock).unwrap();
+    assert!(lock.contains(&format!("\"checksum bar 0.0.1 (registry+{})\" = \"{}\"",
+                                   r::registry(), r::cksum(&c))), "{}", lock);
+});
+
+test!(generate_lockfile_minimal_versions {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "^0.1.2"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    r::mock_pkg("bar", "0.1.2", &[]);
+    r::mock_pkg("bar", "0.1.9", &[]);
+
+    assert_that(p.cargo_process("generate-lockfile").arg("--minimal-versions"),
+                execs().with_status(0));
+    let mut lock = String::new();
+    File::open(&p.root().join("Cargo.lock")).unwrap()
+         .read_to_string(&mut lock).unwrap();
+    assert!(lock.contains("bar 0.1.2"), "{}", lock);
+    assert!(!lock.contains("bar 0.1.9"), "{}", lock);
+
+    assert_that(p.cargo("update"),
+                execs().with_status(0));
+    let mut lock = String::new();
+    File::open(&p.root().join("Cargo.lock")).unwrap()
+         .read_to_string(&mut lock).unwrap();
+    assert!(lock.contains("bar 0.1.9"), "{}", lock);
+
+    assert_that(p.cargo("update").arg("--minimal-versions"),
+                execs().with_status(0));
+    let mut lock = String::new();
+    File::open(&p.root().join("Cargo.lock")).unwrap()
+         .read_to_string(&mut lock).unwrap();
+    assert!(lock.contains("bar 0.1.2"), "{}", lock);
+});
+
+test!(changed_cksum_in_index {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = ">= 0.0.0"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+
+    assert_that(p.cargo_process("generate-lockfile"),
+                execs().with_status(0));
+
+    // Pretend the index was rewritten to point at different contents.
+    let lockfile = p.root().join("Cargo.lock");
+    let mut lock = String::new();
+    File::open(&lockfile).unwrap().read_to_string(&mut lock).unwrap();
+    let lock = lock.lines().map(|l| {
+        if l.starts_with("\"checksum bar ") {
+            let key = l.splitn(1, '=').next().unwrap();
+            format!("{}= \"{}\"", key, r::cksum(b"bogus"))
+        } else {
+            l.to_string()
+        }
+    }).collect::<Vec<_>>().connect("\n");
+    File::create(&lockfile).unwrap().write_all(lock.as_bytes()).unwrap();
+
+    assert_that(p.cargo("build"),
+                execs().with_status(101).with_stderr(format!("\
+checksum for `bar v0.0.1 (registry file://[..])` changed between lock files
+
+expected: {}
+found: [..]
+
+the source of the package may have been tampered with
+", r::cksum(b"bogus"))));
+});
+
 test!(update_registry {
     let p = project("foo")
         .file("Cargo.toml", r#"
@@ -694,6 +804,9 @@ test!(update_publish_then_update {
     let lock = p.root().join("Cargo.lock");
     let mut s = String::new();
     File::open(&lock).unwrap().read_to_string(&mut s).unwrap();
+    // The recorded checksum belongs to 0.1.0, so drop it along the way.
+    let s = s.lines().filter(|l| !l.starts_with("\"checksum "))
+             .collect::<Vec<_>>().connect("\n");
     File::create(&lock).unwrap()
          .write_all(s.replace("0.1.0", "0.1.1").as_bytes()).unwrap();
     println!("second");
@@ -0,0 +1,194 @@
+use std::fs::File;
+use std::io::prelude::*;
+
+use support::{project, execs};
+use support::paths;
+use support::registry as r;
+use support::git;
+
+use hamcrest::assert_that;
+
+fn setup() {
+    r::init();
+}
+
+fn read_lockfile(root: &::std::path::Path) -> String {
+    let mut lock = String::new();
+    File::open(&root.join("Cargo.lock")).unwrap()
+         .read_to_string(&mut lock).unwrap();
+    lock
+}
+
+test!(replace_with_git {
+    r::mock_pkg("bar", "0.1.0", &[]);
+
+    let bar = git::repo(&paths::root().join("bar"))
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn replaced() {}");
+    bar.build();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+
+            [replace]
+            "bar:0.1.0" = {{ git = '{}' }}
+        "#, bar.url()))
+        .file("src/main.rs", "
+            extern crate bar;
+            fn main() { bar::replaced(); }
+        ");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0));
+
+    let lock = read_lockfile(&p.root());
+    assert!(lock.contains(&format!("bar 0.1.0 (git+{}", bar.url())), "{}", lock);
+    assert!(!lock.contains("bar 0.1.0 (registry+"), "{}", lock);
+
+    // The lockfile keeps pointing at the replacement
+    assert_that(p.cargo("build"),
+                execs().with_status(0));
+    assert_that(p.cargo("update"),
+                execs().with_status(0));
+    let lock = read_lockfile(&p.root());
+    assert!(lock.contains(&format!("bar 0.1.0 (git+{}", bar.url())), "{}", lock);
+});
+
+test!(replace_with_path {
+    r::mock_pkg("bar", "0.1.0", &[]);
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+
+            [replace]
+            "bar:0.1.0" = { path = "bar" }
+        "#)
+        .file("src/main.rs", "
+            extern crate bar;
+            fn main() { bar::replaced(); }
+        ")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "pub fn replaced() {}");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0));
+
+    let lock = read_lockfile(&p.root());
+    assert!(lock.contains("\"bar 0.1.0\""), "{}", lock);
+    assert!(!lock.contains("bar 0.1.0 (registry+"), "{}", lock);
+});
+
+test!(replace_only_matching_version {
+    r::mock_pkg("bar", "0.1.0", &[]);
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+
+            [replace]
+            "bar:0.2.0" = { path = "bar" }
+        "#)
+        .file("src/main.rs", "
+            extern crate bar;
+            fn main() {}
+        ")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.2.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("generate-lockfile"),
+                execs().with_status(0));
+
+    let lock = read_lockfile(&p.root());
+    assert!(lock.contains("bar 0.1.0 (registry+"), "{}", lock);
+});
+
+test!(replacement_with_wrong_version {
+    r::mock_pkg("bar", "0.1.0", &[]);
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+
+            [replace]
+            "bar:0.1.0" = { path = "bar" }
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("generate-lockfile"),
+                execs().with_status(101).with_stderr("\
+the replacement for `bar v0.1.0 (registry [..])` does not provide version \
+0.1.0 of `bar`
+location searched: [..]
+"));
+});
+
+test!(replacement_with_version_req {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [replace]
+            "bar:0.1.0" = { path = "bar", version = "0.1.0" }
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr("\
+failed to parse manifest at `[..]`
+
+Caused by:
+  replacements cannot specify a version requirement, but found one for \
+`bar:0.1.0`
+"));
+});
@@ -0,0 +1,154 @@
+use std::old_path;
+
+use support::{project, execs};
+use support::{COMPILING, RUNNING};
+use hamcrest::assert_that;
+
+fn setup() {
+}
+
+test!(fails_when_trying_to_build_main_and_lib_with_args {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("rustc").arg("-v").arg("--").arg("--cfg")
+                 .arg("foo"),
+                execs().with_status(101).with_stderr("\
+extra arguments to `rustc` can only be passed to one target, consider \
+filtering the package by passing e.g. `--lib` or `--bin NAME` to specify \
+a single target"));
+});
+
+test!(build_lib_for_foo {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("src/lib.rs", r#"
+            #[cfg(foo)]
+            pub fn foo() {}
+        "#);
+
+    assert_that(p.cargo_process("rustc").arg("--lib").arg("-v").arg("--")
+                 .arg("--cfg").arg("foo"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({url})
+{running} `rustc src{sep}lib.rs --crate-name foo --crate-type lib [..] \
+        --emit=dep-info,link --cfg foo -L [..]`
+",
+            compiling = COMPILING, running = RUNNING, sep = old_path::SEP,
+            url = p.url())));
+});
+
+test!(build_only_selected_bin {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [[bin]]
+            name = "a"
+
+            [[bin]]
+            name = "b"
+        "#)
+        .file("src/a.rs", "fn main() {}")
+        .file("src/b.rs", "#[cfg(foo)] fn main() {}");
+
+    assert_that(p.cargo_process("rustc").arg("--bin").arg("b").arg("-v")
+                 .arg("--").arg("--cfg").arg("foo"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({url})
+{running} `rustc src{sep}b.rs --crate-name b --crate-type bin [..] \
+        --emit=dep-info,link --cfg foo -L [..]`
+",
+            compiling = COMPILING, running = RUNNING, sep = old_path::SEP,
+            url = p.url())));
+
+    assert_that(p.cargo("rustc").arg("--bin").arg("c"),
+                execs().with_status(101)
+                       .with_stderr("no bin target named `c`"));
+});
+
+test!(build_bin_using_own_lib {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            #[cfg(not(foo))]
+            pub fn foo() {}
+        "#)
+        .file("src/main.rs", r#"
+            extern crate foo;
+            #[cfg(foo)]
+            fn main() { foo::foo() }
+        "#);
+
+    assert_that(p.cargo_process("rustc").arg("--bin").arg("foo").arg("-v")
+                 .arg("--").arg("--cfg").arg("foo"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({url})
+{running} `rustc src{sep}lib.rs --crate-name foo --crate-type lib [..] \
+        --emit=dep-info,link -L [..]`
+{running} `rustc src{sep}main.rs --crate-name foo --crate-type bin [..] \
+        --emit=dep-info,link --cfg foo -L [..]`
+",
+            compiling = COMPILING, running = RUNNING, sep = old_path::SEP,
+            url = p.url())));
+});
+
+test!(args_not_passed_to_dependencies {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", r#"
+            extern crate bar;
+            #[cfg(foo)]
+            fn main() { bar::bar() }
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", r#"
+            #[cfg(not(foo))]
+            pub fn bar() {}
+        "#);
+
+    assert_that(p.cargo_process("rustc").arg("-v").arg("--").arg("--cfg")
+                 .arg("foo"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} bar v0.0.1 ([..])
+{running} `rustc [..]lib.rs [..] --emit=dep-info,link -L [..]`
+{compiling} foo v0.0.1 ({url})
+{running} `rustc src{sep}main.rs [..] --emit=dep-info,link --cfg foo -L [..]`
+",
+            compiling = COMPILING, running = RUNNING, sep = old_path::SEP,
+            url = p.url())));
+});
@@ -0,0 +1,91 @@
+use std::old_path;
+
+use support::{project, execs};
+use support::{COMPILING, RUNNING};
+use hamcrest::assert_that;
+
+fn setup() {
+}
+
+test!(env_rustflags_normal_source {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "#[cfg(foo)] fn main() {}");
+
+    assert_that(p.cargo_process("build").arg("-v")
+                 .env("RUSTFLAGS", "--cfg  foo"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({url})
+{running} `rustc src{sep}main.rs [..] --emit=dep-info,link --cfg foo -L [..]`
+",
+            compiling = COMPILING, running = RUNNING, sep = old_path::SEP,
+            url = p.url())));
+});
+
+test!(env_rustflags_take_precedence_over_config {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "#[cfg(foo)] fn main() {}")
+        .file(".cargo/config", r#"
+            [build]
+            rustflags = "--cfg bar"
+        "#);
+
+    assert_that(p.cargo_process("build").env("RUSTFLAGS", "--cfg foo"),
+                execs().with_status(0));
+});
+
+test!(target_rustflags_take_precedence_over_build {
+    let (_, target) = ::cargo::ops::rustc_version().unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "#[cfg(foo)] fn main() {}")
+        .file(".cargo/config", format!(r#"
+            [build]
+            rustflags = "--cfg bar"
+
+            [target.{}]
+            rustflags = ["--cfg", "foo"]
+        "#, target).as_slice());
+
+    assert_that(p.cargo_process("build").env_remove("RUSTFLAGS"),
+                execs().with_status(0));
+});
+
+test!(changing_rustflags_rebuilds {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("build").env("RUSTFLAGS", "--cfg foo"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({url})
+", compiling = COMPILING, url = p.url())));
+    assert_that(p.cargo("build").env("RUSTFLAGS", "--cfg foo"),
+                execs().with_status(0).with_stdout(""));
+    assert_that(p.cargo("build").env("RUSTFLAGS", "--cfg bar"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({url})
+", compiling = COMPILING, url = p.url())));
+});
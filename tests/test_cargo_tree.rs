@@ -0,0 +1,106 @@
+use support::{project, execs, ProjectBuilder};
+use support::registry as r;
+
+use hamcrest::assert_that;
+
+fn setup() {
+    r::init();
+}
+
+fn foo() -> ProjectBuilder {
+    r::mock_pkg("baz", "0.1.0", &[]);
+    r::mock_pkg("baz", "0.2.0", &[]);
+    r::mock_pkg("bar", "0.1.0", &[("baz", "^0.1.0", "normal")]);
+
+    project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+
+            [dev-dependencies]
+            baz = "0.2.0"
+
+            [features]
+            a = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+}
+
+test!(simple {
+    let p = foo();
+    assert_that(p.cargo_process("generate-lockfile"),
+                execs().with_status(0));
+
+    assert_that(p.cargo("tree"),
+                execs().with_status(0).with_stdout(format!("\
+foo v0.0.1 ({dir})
+├── bar v0.1.0 (registry file://[..])
+│   └── baz v0.1.0 (registry file://[..])
+[dev-dependencies]
+└── baz v0.2.0 (registry file://[..])
+", dir = p.url())));
+});
+
+test!(invert {
+    let p = foo();
+    assert_that(p.cargo_process("generate-lockfile"),
+                execs().with_status(0));
+
+    assert_that(p.cargo("tree").arg("--invert").arg("baz:0.1.0"),
+                execs().with_status(0).with_stdout(format!("\
+baz v0.1.0 (registry file://[..])
+└── bar v0.1.0 (registry file://[..])
+    └── foo v0.0.1 ({dir})
+", dir = p.url())));
+});
+
+test!(duplicates {
+    let p = foo();
+    assert_that(p.cargo_process("generate-lockfile"),
+                execs().with_status(0));
+
+    assert_that(p.cargo("tree").arg("--duplicates"),
+                execs().with_status(0).with_stdout(format!("\
+baz v0.1.0 (registry file://[..])
+└── bar v0.1.0 (registry file://[..])
+    └── foo v0.0.1 ({dir})
+
+baz v0.2.0 (registry file://[..])
+[dev-dependencies]
+└── foo v0.0.1 ({dir})
+", dir = p.url())));
+});
+
+test!(format_with_features {
+    let p = foo();
+    assert_that(p.cargo_process("generate-lockfile"),
+                execs().with_status(0));
+
+    assert_that(p.cargo("tree").arg("--features").arg("a")
+                 .arg("--format").arg("{n}:{v} [{f}]"),
+                execs().with_status(0).with_stdout("\
+foo:0.0.1 [a]
+├── bar:0.1.0 []
+│   └── baz:0.1.0 []
+[dev-dependencies]
+└── baz:0.2.0 []
+"));
+
+    assert_that(p.cargo("tree").arg("--format").arg("{x}"),
+                execs().with_status(101).with_stderr("\
+unsupported placeholder `{x}` in format `{x}`
+"));
+});
+
+test!(requires_lockfile {
+    let p = foo();
+    assert_that(p.cargo_process("tree"),
+                execs().with_status(101).with_stderr("\
+A Cargo.lock must exist for this command
+"));
+});
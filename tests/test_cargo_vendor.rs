@@ -0,0 +1,176 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+
+use serialize::json::Json;
+
+use support::{project, execs, cargo_dir};
+use support::{COMPILING, UPDATING, DOWNLOADING, VENDORING};
+use support::paths::{self, CargoPathExt};
+use support::registry as r;
+use support::git;
+
+use hamcrest::{assert_that, existing_file, existing_dir, is_not};
+
+fn setup() {
+    r::init();
+}
+
+test!(vendor_registry {
+    r::mock_pkg("bar", "0.1.0", &[]);
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("vendor"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `{reg}`
+{downloading} bar v0.1.0 (registry file://[..])
+{vendoring} bar v0.1.0 (registry file://[..])
+# Use the vendored copies of the dependencies instead of their sources
+[registry]
+directory = \"vendor\"
+",
+        updating = UPDATING,
+        downloading = DOWNLOADING,
+        vendoring = VENDORING,
+        reg = r::registry())));
+
+    assert_that(&p.root().join("vendor/bar-0.1.0/Cargo.toml"), existing_file());
+    assert_that(&p.root().join("vendor/bar-0.1.0/.cargo-checksum.json"),
+                existing_file());
+});
+
+test!(vendor_git {
+    let bar = git::repo(&paths::root().join("bar"))
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.2.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn bar() {}");
+    bar.build();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()))
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("vendor").arg("third-party"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} git repository `{git}`
+{vendoring} bar v0.2.0 ({git}#[..])
+# Use the vendored copies of the dependencies instead of their sources
+paths = [
+    \"third-party/bar-0.2.0\",
+]
+",
+        updating = UPDATING,
+        vendoring = VENDORING,
+        git = bar.url())));
+
+    let vendored = p.root().join("third-party/bar-0.2.0");
+    assert_that(&vendored.join("src/lib.rs"), existing_file());
+    assert_that(&vendored.join(".git"), is_not(existing_dir()));
+
+    // Every copied file is listed along with the revision of the package.
+    let mut s = String::new();
+    File::open(&vendored.join(".cargo-checksum.json")).unwrap()
+         .read_to_string(&mut s).unwrap();
+    let json = Json::from_str(&s).unwrap();
+    let files = json.find("files").unwrap().as_object().unwrap();
+    let mut names = files.keys().map(|s| &s[..]).collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["Cargo.toml", "src/lib.rs"]);
+    assert_eq!(json.find("package").unwrap().as_string().map(|s| s.len()),
+               Some(40));
+});
+
+test!(vendored_build_without_network {
+    r::mock_pkg("bar", "0.1.0", &[]);
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/main.rs", "
+            extern crate bar;
+            fn main() {}
+        ");
+
+    assert_that(p.cargo_process("vendor"),
+                execs().with_status(0));
+
+    fs::create_dir_all(&p.root().join(".cargo")).unwrap();
+    File::create(&p.root().join(".cargo/config")).unwrap().write_all(br#"
+        [registry]
+        directory = "vendor"
+    "#).unwrap();
+
+    // Nothing fetched from the registry is needed anymore, not even its index.
+    paths::home().join(".cargo/registry").rm_rf().unwrap();
+
+    assert_that(p.process(&cargo_dir().join("cargo")).arg("--offline")
+                 .arg("build"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} bar v0.1.0 (directory {dir}/vendor)
+{compiling} foo v0.0.1 ({dir})
+",
+        compiling = COMPILING,
+        dir = p.url())));
+});
+
+test!(vendor_removes_stale_packages {
+    r::mock_pkg("bar", "0.1.0", &[]);
+    r::mock_pkg("bar", "0.1.1", &[]);
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("generate-lockfile"),
+                execs().with_status(0));
+    assert_that(p.cargo("update").arg("-p").arg("bar")
+                 .arg("--precise").arg("0.1.0"),
+                execs().with_status(0));
+    assert_that(p.cargo("vendor"),
+                execs().with_status(0));
+    assert_that(&p.root().join("vendor/bar-0.1.0/Cargo.toml"), existing_file());
+
+    assert_that(p.cargo("update"),
+                execs().with_status(0));
+    assert_that(p.cargo("vendor"),
+                execs().with_status(0));
+    assert_that(&p.root().join("vendor/bar-0.1.1/Cargo.toml"), existing_file());
+    assert_that(&p.root().join("vendor/bar-0.1.0"), is_not(existing_dir()));
+});
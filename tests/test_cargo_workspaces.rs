@@ -0,0 +1,197 @@
+use std::fs::File;
+use std::io::prelude::*;
+
+use support::{project, execs};
+use support::{COMPILING, UPDATING, DOWNLOADING, RUNNING};
+use support::registry as r;
+
+use hamcrest::{assert_that, existing_file, existing_dir, is_not};
+
+fn setup() {
+    r::init();
+}
+
+test!(member_uses_root_lockfile_and_target {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/main.rs", "fn main() {}");
+    p.build();
+
+    assert_that(p.cargo("build").cwd(&p.root().join("bar")),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} bar v0.1.0 ({dir})
+",
+        compiling = COMPILING,
+        dir = p.url())));
+
+    assert_that(&p.bin("bar"), existing_file());
+    assert_that(&p.root().join("Cargo.lock"), existing_file());
+    assert_that(&p.root().join("bar/Cargo.lock"), is_not(existing_file()));
+    assert_that(&p.root().join("bar/target"), is_not(existing_dir()));
+
+    // Both members are listed in the lockfile of the root.
+    let mut lock = String::new();
+    File::open(&p.root().join("Cargo.lock")).unwrap()
+         .read_to_string(&mut lock).unwrap();
+    assert!(lock.contains("name = \"bar\""), "{}", lock);
+});
+
+test!(build_all_members_shares_dependencies {
+    r::mock_pkg("baz", "0.1.0", &[]);
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            baz = "0.1.0"
+
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("src/main.rs", "
+            extern crate baz;
+            fn main() {}
+        ")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            baz = "0.1.0"
+        "#)
+        .file("bar/src/main.rs", "
+            extern crate baz;
+            fn main() {}
+        ");
+
+    assert_that(p.cargo_process("build").arg("--all"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `{reg}`
+{downloading} baz v0.1.0 (registry file://[..])
+{compiling} baz v0.1.0 (registry file://[..])
+{compiling} foo v0.1.0 ({dir})
+{compiling} bar v0.1.0 ({dir})
+",
+        updating = UPDATING,
+        downloading = DOWNLOADING,
+        compiling = COMPILING,
+        reg = r::registry(),
+        dir = p.url())));
+
+    assert_that(&p.bin("foo"), existing_file());
+    assert_that(&p.bin("bar"), existing_file());
+});
+
+test!(test_several_members {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("src/main.rs", "
+            fn main() {}
+            #[test] fn test_foo() {}
+        ")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/main.rs", "
+            fn main() {}
+            #[test] fn test_bar() {}
+        ");
+
+    assert_that(p.cargo_process("test").arg("-p").arg("bar")
+                 .arg("-p").arg("foo"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} bar v0.1.0 ({dir})
+{compiling} foo v0.1.0 ({dir})
+{running} target[..]bar-[..]
+
+running 1 test
+test test_bar ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured
+
+{running} target[..]foo-[..]
+
+running 1 test
+test test_foo ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured
+
+",
+        compiling = COMPILING,
+        running = RUNNING,
+        dir = p.url())));
+});
+
+test!(clean_several_members {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [workspace]
+            members = ["bar", "baz"]
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/main.rs", "fn main() {}")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("baz/src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("build").arg("--all"),
+                execs().with_status(0));
+    assert_that(&p.bin("bar"), existing_file());
+
+    assert_that(p.cargo("clean").arg("-p").arg("bar").arg("-p").arg("baz"),
+                execs().with_status(0));
+    assert_that(&p.bin("foo"), existing_file());
+    assert_that(&p.bin("bar"), is_not(existing_file()));
+    assert_that(&p.bin("baz"), is_not(existing_file()));
+
+    assert_that(p.cargo("clean").arg("--all").cwd(&p.root().join("bar")),
+                execs().with_status(0));
+    assert_that(&p.build_dir(), is_not(existing_dir()));
+});
@@ -42,14 +42,23 @@ mod test_cargo_doc;
 mod test_cargo_features;
 mod test_cargo_fetch;
 mod test_cargo_freshness;
+mod test_cargo_frozen;
 mod test_cargo_generate_lockfile;
+mod test_cargo_local_sources;
+mod test_cargo_metadata;
 mod test_cargo_new;
 mod test_cargo_package;
 mod test_cargo_profiles;
 mod test_cargo_publish;
 mod test_cargo_registry;
+mod test_cargo_replace;
 mod test_cargo_run;
+mod test_cargo_rustc;
+mod test_cargo_rustflags;
 mod test_cargo_search;
 mod test_cargo_test;
+mod test_cargo_tree;
+mod test_cargo_vendor;
 mod test_cargo_version;
+mod test_cargo_workspaces;
 mod test_shell;